@@ -1,3 +1,39 @@
 mod openai;
 
-pub use openai::OpenAIClient;
+pub use openai::{
+    decode_pcm16, encode_pcm16, example_token_limit, validate_dataset, validate_dataset_file,
+    ApiError, ApiErrorBody, ApiResponse, Assistant, AssistantTool, Batch, BatchBuilder,
+    BatchEndpoint, BatchRequestCounts, BatchRequestError, BatchResults, BatchStatus, Budget,
+    BudgetAlert, BudgetExceeded, BudgetLimit, BudgetManager, BudgetReservation, BudgetScope,
+    ChatCompletion, ChatFunction, ChatMessage, ChatOptions, ChatResponseChoice,
+    ChatResponseMessage, ChatRole, Choice, ComparisonOperator, CompleteUploadOptions, Completion,
+    CompletionOptions, CompoundOperator, CreateAssistantOptions, CreateBatchOptions,
+    CreateEmbeddingsOptions, CreateFineTuningJobOptions, CreateImgOptions,
+    CreateImgVariationsOptions, CreateMessageOptions, CreateModerationOptions,
+    CreateResponseOptions, CreateRunOptions, CreateThreadOptions, CreateUploadOptions,
+    CreateVectorStoreFileBatchOptions, CreateVectorStoreFileOptions, CreateVectorStoreOptions,
+    DatasetIssue, DatasetIssueKind, DatasetReport, DeletionStatus, EditImgOptions, Embedding,
+    Embeddings, ExpiresAfter, FilePurpose, FineTuningCheckpoint, FineTuningJob, FineTuningJobEvent,
+    FineTuningJobStatus, Hyperparameters, Img, ImgBackground, ImgFormat, ImgOutputFormat,
+    ImgQuality, ImgResponse, ImgSize, ImgStyle, ImgType, InputContent, InputFlagged, InvalidImg,
+    InvalidImgOptions, LargeUploadOptions, ListFilesOptions, ListOptions, ListOrder, ListResponse,
+    ListVectorStoreFilesOptions, MessageContent, MessageImageFile, MessageImageUrl, MessageText,
+    ModelPricing, ModerationCategories, ModerationGate, ModerationImageUrl, ModerationInput,
+    ModerationInputPart, ModerationInputType, ModerationResponse, ModerationResult,
+    ModifyAssistantOptions, ModifyMessageOptions, ModifyThreadOptions, ModifyVectorStoreOptions,
+    OpenAIClient, OpenAIFile, OpenAIGetModelsResponse, OpenAIModel, OpenAIModelPermission,
+    OutputContent, OutputFlagged, PollOptions, PollTimeout, Pricing, RankingOptions,
+    RateLimitHeaders, RateLimiter, RateLimits, RealtimeClientEvent, RealtimeError,
+    RealtimeReceiver, RealtimeResponseConfig, RealtimeSender, RealtimeServerEvent, RealtimeSession,
+    RealtimeSessionConfig, RequiredAction, Response, ResponseFormat, ResponseInput,
+    ResponseInputItem, ResponseMetadata, ResponseOutputItem, ResponseStatus, ResponseStreamEvent,
+    ResponseTextConfig, ResponseTool, ResponseUsage, Run, RunStatus, RunStep, SearchFilter,
+    SearchResult, SearchResultContent, SearchResults, SearchVectorStoreOptions,
+    SubmitToolOutputsAction, Thread, ThreadMessage, ToolCall, ToolCallFunction, ToolOutput,
+    TrainingDataset, TrainingExample, Upload, UploadCancelled, UploadFileOptions, UploadPart,
+    UploadSource, UploadStatus, Usage, UsageSnapshot, UsageTotals, UsageTracker, VectorStore,
+    VectorStoreFile, VectorStoreFileBatch, VectorStoreFileCounts, VectorStoreFileStatus,
+    VectorStoreStatus, MAX_IMG_UPLOAD_SIZE, MAX_UPLOAD_PART_SIZE,
+};
+#[cfg(feature = "image")]
+pub use openai::{prepare_img, ImgMask, PrepareImgOptions, SquareMode};
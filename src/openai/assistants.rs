@@ -0,0 +1,836 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use reqwest::{Method, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::OpenAIClient;
+
+use super::chat::{ChatFunction, ChatRole};
+use super::deletion::DeletionStatus;
+use super::list::{ListOptions, ListResponse};
+use super::poll::{Backoff, PollOptions};
+use super::usage::Usage;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum AssistantTool {
+    #[serde(rename = "code_interpreter")]
+    CodeInterpreter,
+    #[serde(rename = "file_search")]
+    FileSearch,
+    #[serde(rename = "function")]
+    Function { function: ChatFunction },
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Assistant {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub model: String,
+    pub instructions: Option<String>,
+    #[serde(default)]
+    pub tools: Vec<AssistantTool>,
+    pub tool_resources: Option<Value>,
+    pub metadata: Option<HashMap<String, String>>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub response_format: Option<Value>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateAssistantOptions {
+    pub model: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub instructions: Option<String>,
+    pub tools: Option<Vec<AssistantTool>>,
+    pub tool_resources: Option<Value>,
+    pub metadata: Option<HashMap<String, String>>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub response_format: Option<Value>,
+}
+
+impl CreateAssistantOptions {
+    pub fn default(model: &str) -> Self {
+        Self {
+            model: model.to_owned(),
+            name: None,
+            description: None,
+            instructions: None,
+            tools: None,
+            tool_resources: None,
+            metadata: None,
+            temperature: None,
+            top_p: None,
+            response_format: None,
+        }
+    }
+}
+
+/// Only the fields that are set are changed.
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ModifyAssistantOptions {
+    pub model: Option<String>,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub instructions: Option<String>,
+    pub tools: Option<Vec<AssistantTool>>,
+    pub tool_resources: Option<Value>,
+    pub metadata: Option<HashMap<String, String>>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub response_format: Option<Value>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Thread {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub tool_resources: Option<Value>,
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CreateThreadOptions {
+    pub messages: Option<Vec<CreateMessageOptions>>,
+    pub tool_resources: Option<Value>,
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ModifyThreadOptions {
+    pub tool_resources: Option<Value>,
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageText {
+    pub value: String,
+    #[serde(default)]
+    pub annotations: Vec<Value>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageImageFile {
+    pub file_id: String,
+    pub detail: Option<String>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageImageUrl {
+    pub url: String,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum MessageContent {
+    #[serde(rename = "text")]
+    Text { text: MessageText },
+    #[serde(rename = "image_file")]
+    ImageFile { image_file: MessageImageFile },
+    #[serde(rename = "image_url")]
+    ImageUrl { image_url: MessageImageUrl },
+    #[serde(rename = "refusal")]
+    Refusal { refusal: String },
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadMessage {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub thread_id: String,
+    pub status: Option<String>,
+    pub role: ChatRole,
+    pub content: Vec<MessageContent>,
+    pub assistant_id: Option<String>,
+    pub run_id: Option<String>,
+    pub attachments: Option<Vec<Value>>,
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+impl ThreadMessage {
+    /// The message's text parts joined by newlines.
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|content| match content {
+                MessageContent::Text { text } => Some(text.value.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<&str>>()
+            .join("\n")
+    }
+}
+
+/// `role` must be [`ChatRole::User`] or [`ChatRole::Assistant`].
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateMessageOptions {
+    pub role: ChatRole,
+    pub content: String,
+    pub attachments: Option<Vec<Value>>,
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+impl CreateMessageOptions {
+    pub fn default(role: ChatRole, content: &str) -> Self {
+        Self {
+            role,
+            content: content.to_owned(),
+            attachments: None,
+            metadata: None,
+        }
+    }
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ModifyMessageOptions {
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateRunOptions {
+    pub assistant_id: String,
+    pub model: Option<String>,
+    pub instructions: Option<String>,
+    pub additional_instructions: Option<String>,
+    pub additional_messages: Option<Vec<CreateMessageOptions>>,
+    pub tools: Option<Vec<AssistantTool>>,
+    pub metadata: Option<HashMap<String, String>>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub max_prompt_tokens: Option<u64>,
+    pub max_completion_tokens: Option<u64>,
+    pub tool_choice: Option<Value>,
+    pub parallel_tool_calls: Option<bool>,
+    pub response_format: Option<Value>,
+}
+
+impl CreateRunOptions {
+    pub fn default(assistant_id: &str) -> Self {
+        Self {
+            assistant_id: assistant_id.to_owned(),
+            model: None,
+            instructions: None,
+            additional_instructions: None,
+            additional_messages: None,
+            tools: None,
+            metadata: None,
+            temperature: None,
+            top_p: None,
+            max_prompt_tokens: None,
+            max_completion_tokens: None,
+            tool_choice: None,
+            parallel_tool_calls: None,
+            response_format: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RunStatus {
+    #[serde(rename = "queued")]
+    Queued,
+    #[serde(rename = "in_progress")]
+    InProgress,
+    #[serde(rename = "requires_action")]
+    RequiresAction,
+    #[serde(rename = "cancelling")]
+    Cancelling,
+    #[serde(rename = "cancelled")]
+    Cancelled,
+    #[serde(rename = "failed")]
+    Failed,
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "incomplete")]
+    Incomplete,
+    #[serde(rename = "expired")]
+    Expired,
+}
+
+impl RunStatus {
+    /// Whether the run has stopped and will not change status again.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            RunStatus::Cancelled
+                | RunStatus::Failed
+                | RunStatus::Completed
+                | RunStatus::Incomplete
+                | RunStatus::Expired
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCallFunction {
+    pub name: String,
+    /// JSON-encoded arguments, as generated by the model
+    pub arguments: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub call_type: String,
+    pub function: ToolCallFunction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmitToolOutputsAction {
+    pub tool_calls: Vec<ToolCall>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequiredAction {
+    #[serde(rename = "type")]
+    pub action_type: String,
+    pub submit_tool_outputs: SubmitToolOutputsAction,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Run {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub thread_id: String,
+    pub assistant_id: String,
+    pub status: RunStatus,
+    pub required_action: Option<RequiredAction>,
+    pub last_error: Option<Value>,
+    pub expires_at: Option<u64>,
+    pub started_at: Option<u64>,
+    pub cancelled_at: Option<u64>,
+    pub failed_at: Option<u64>,
+    pub completed_at: Option<u64>,
+    pub incomplete_details: Option<Value>,
+    pub model: String,
+    pub instructions: Option<String>,
+    #[serde(default)]
+    pub tools: Vec<AssistantTool>,
+    pub metadata: Option<HashMap<String, String>>,
+    pub usage: Option<Usage>,
+}
+
+impl Run {
+    /// The function calls the run is waiting on when its status is `requires_action`.
+    pub fn tool_calls(&self) -> &[ToolCall] {
+        self.required_action
+            .as_ref()
+            .map_or(&[], |action| &action.submit_tool_outputs.tool_calls)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolOutput {
+    pub tool_call_id: String,
+    pub output: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SubmitToolOutputsOptions<'a> {
+    tool_outputs: &'a [ToolOutput],
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunStep {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub assistant_id: String,
+    pub thread_id: String,
+    pub run_id: String,
+    /// `message_creation` or `tool_calls`
+    #[serde(rename = "type")]
+    pub step_type: String,
+    pub status: String,
+    pub step_details: Value,
+    pub last_error: Option<Value>,
+    pub expired_at: Option<u64>,
+    pub cancelled_at: Option<u64>,
+    pub failed_at: Option<u64>,
+    pub completed_at: Option<u64>,
+    pub metadata: Option<HashMap<String, String>>,
+    pub usage: Option<Usage>,
+}
+
+impl OpenAIClient {
+    /// Like [`OpenAIClient::request`], with the `OpenAI-Beta` header the Assistants API requires.
+    pub(crate) fn beta_request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.request(method, path)
+            .header("OpenAI-Beta", "assistants=v2")
+    }
+
+    pub async fn create_assistant(
+        &self,
+        opts: &CreateAssistantOptions,
+    ) -> Result<Assistant, Box<dyn Error + Send + Sync>> {
+        let request = self.beta_request(Method::POST, "/assistants").json(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn list_assistants(
+        &self,
+        opts: &ListOptions,
+    ) -> Result<ListResponse<Assistant>, Box<dyn Error + Send + Sync>> {
+        let request = self.beta_request(Method::GET, "/assistants").query(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn get_assistant(
+        &self,
+        assistant_id: &str,
+    ) -> Result<Assistant, Box<dyn Error + Send + Sync>> {
+        let request = self.beta_request(Method::GET, &format!("/assistants/{assistant_id}"));
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn modify_assistant(
+        &self,
+        assistant_id: &str,
+        opts: &ModifyAssistantOptions,
+    ) -> Result<Assistant, Box<dyn Error + Send + Sync>> {
+        let request = self
+            .beta_request(Method::POST, &format!("/assistants/{assistant_id}"))
+            .json(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn delete_assistant(
+        &self,
+        assistant_id: &str,
+    ) -> Result<DeletionStatus, Box<dyn Error + Send + Sync>> {
+        let request = self.beta_request(Method::DELETE, &format!("/assistants/{assistant_id}"));
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn create_thread(
+        &self,
+        opts: &CreateThreadOptions,
+    ) -> Result<Thread, Box<dyn Error + Send + Sync>> {
+        let request = self.beta_request(Method::POST, "/threads").json(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn get_thread(
+        &self,
+        thread_id: &str,
+    ) -> Result<Thread, Box<dyn Error + Send + Sync>> {
+        let request = self.beta_request(Method::GET, &format!("/threads/{thread_id}"));
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn modify_thread(
+        &self,
+        thread_id: &str,
+        opts: &ModifyThreadOptions,
+    ) -> Result<Thread, Box<dyn Error + Send + Sync>> {
+        let request = self
+            .beta_request(Method::POST, &format!("/threads/{thread_id}"))
+            .json(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn delete_thread(
+        &self,
+        thread_id: &str,
+    ) -> Result<DeletionStatus, Box<dyn Error + Send + Sync>> {
+        let request = self.beta_request(Method::DELETE, &format!("/threads/{thread_id}"));
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn create_message(
+        &self,
+        thread_id: &str,
+        opts: &CreateMessageOptions,
+    ) -> Result<ThreadMessage, Box<dyn Error + Send + Sync>> {
+        let request = self
+            .beta_request(Method::POST, &format!("/threads/{thread_id}/messages"))
+            .json(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    /// Messages are returned newest first unless `opts.order` says otherwise.
+    pub async fn list_messages(
+        &self,
+        thread_id: &str,
+        opts: &ListOptions,
+    ) -> Result<ListResponse<ThreadMessage>, Box<dyn Error + Send + Sync>> {
+        let request = self
+            .beta_request(Method::GET, &format!("/threads/{thread_id}/messages"))
+            .query(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn get_message(
+        &self,
+        thread_id: &str,
+        message_id: &str,
+    ) -> Result<ThreadMessage, Box<dyn Error + Send + Sync>> {
+        let request = self.beta_request(
+            Method::GET,
+            &format!("/threads/{thread_id}/messages/{message_id}"),
+        );
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn modify_message(
+        &self,
+        thread_id: &str,
+        message_id: &str,
+        opts: &ModifyMessageOptions,
+    ) -> Result<ThreadMessage, Box<dyn Error + Send + Sync>> {
+        let request = self
+            .beta_request(
+                Method::POST,
+                &format!("/threads/{thread_id}/messages/{message_id}"),
+            )
+            .json(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn delete_message(
+        &self,
+        thread_id: &str,
+        message_id: &str,
+    ) -> Result<DeletionStatus, Box<dyn Error + Send + Sync>> {
+        let request = self.beta_request(
+            Method::DELETE,
+            &format!("/threads/{thread_id}/messages/{message_id}"),
+        );
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn create_run(
+        &self,
+        thread_id: &str,
+        opts: &CreateRunOptions,
+    ) -> Result<Run, Box<dyn Error + Send + Sync>> {
+        let request = self
+            .beta_request(Method::POST, &format!("/threads/{thread_id}/runs"))
+            .json(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn list_runs(
+        &self,
+        thread_id: &str,
+        opts: &ListOptions,
+    ) -> Result<ListResponse<Run>, Box<dyn Error + Send + Sync>> {
+        let request = self
+            .beta_request(Method::GET, &format!("/threads/{thread_id}/runs"))
+            .query(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn get_run(
+        &self,
+        thread_id: &str,
+        run_id: &str,
+    ) -> Result<Run, Box<dyn Error + Send + Sync>> {
+        let request =
+            self.beta_request(Method::GET, &format!("/threads/{thread_id}/runs/{run_id}"));
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn cancel_run(
+        &self,
+        thread_id: &str,
+        run_id: &str,
+    ) -> Result<Run, Box<dyn Error + Send + Sync>> {
+        let request = self.beta_request(
+            Method::POST,
+            &format!("/threads/{thread_id}/runs/{run_id}/cancel"),
+        );
+        Ok(self.send_json(request).await?.data)
+    }
+
+    /// Answers the tool calls of a run that `requires_action`. Every call in
+    /// [`Run::tool_calls`] needs an output.
+    pub async fn submit_tool_outputs(
+        &self,
+        thread_id: &str,
+        run_id: &str,
+        tool_outputs: &[ToolOutput],
+    ) -> Result<Run, Box<dyn Error + Send + Sync>> {
+        let request = self
+            .beta_request(
+                Method::POST,
+                &format!("/threads/{thread_id}/runs/{run_id}/submit_tool_outputs"),
+            )
+            .json(&SubmitToolOutputsOptions { tool_outputs });
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn list_run_steps(
+        &self,
+        thread_id: &str,
+        run_id: &str,
+        opts: &ListOptions,
+    ) -> Result<ListResponse<RunStep>, Box<dyn Error + Send + Sync>> {
+        let request = self
+            .beta_request(
+                Method::GET,
+                &format!("/threads/{thread_id}/runs/{run_id}/steps"),
+            )
+            .query(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    /// Polls a run until it finishes or stops to wait for tool outputs. Check the returned run's
+    /// `status`: if it is `requires_action`, answer [`Run::tool_calls`] with
+    /// [`OpenAIClient::submit_tool_outputs`] and wait again.
+    pub async fn wait_for_run(
+        &self,
+        thread_id: &str,
+        run_id: &str,
+        poll: &PollOptions,
+    ) -> Result<Run, Box<dyn Error + Send + Sync>> {
+        let mut backoff = Backoff::new(poll);
+        loop {
+            let run = self.get_run(thread_id, run_id).await?;
+            if run.status.is_finished() || run.status == RunStatus::RequiresAction {
+                return Ok(run);
+            }
+            backoff.wait().await?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+    use std::time::Duration;
+    use wiremock::matchers::{body_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn run_json(status: &str) -> Value {
+        let mut run = json!({
+            "id": "run-1",
+            "object": "thread.run",
+            "created_at": 1699063290,
+            "thread_id": "thread-1",
+            "assistant_id": "asst-1",
+            "status": status,
+            "model": "gpt-4o",
+            "instructions": null,
+            "tools": [{
+                "type": "function",
+                "function": {
+                    "name": "get_pond_temperature",
+                    "parameters": { "type": "object", "properties": {} }
+                }
+            }],
+            "metadata": {},
+            "usage": null
+        });
+        if status == "requires_action" {
+            run["required_action"] = json!({
+                "type": "submit_tool_outputs",
+                "submit_tool_outputs": {
+                    "tool_calls": [{
+                        "id": "call-1",
+                        "type": "function",
+                        "function": { "name": "get_pond_temperature", "arguments": "{}" }
+                    }]
+                }
+            });
+        }
+        if status == "completed" {
+            run["usage"] =
+                json!({ "prompt_tokens": 120, "completion_tokens": 15, "total_tokens": 135 });
+        }
+        run
+    }
+
+    #[tokio::test]
+    pub async fn test_create_assistant() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/assistants"))
+            .and(header("OpenAI-Beta", "assistants=v2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "asst-1",
+                "object": "assistant",
+                "created_at": 1698984975,
+                "name": "Pond Keeper",
+                "description": null,
+                "model": "gpt-4o",
+                "instructions": "You look after the pond.",
+                "tools": [{ "type": "code_interpreter" }],
+                "metadata": {},
+                "top_p": 1.0,
+                "temperature": 1.0,
+                "response_format": "auto"
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let mut opts = CreateAssistantOptions::default("gpt-4o");
+        opts.name = Some("Pond Keeper".to_owned());
+        opts.tools = Some(vec![
+            AssistantTool::CodeInterpreter,
+            AssistantTool::Function {
+                function: ChatFunction {
+                    name: "get_pond_temperature".to_owned(),
+                    description: None,
+                    parameters: json!({ "type": "object", "properties": {} }),
+                },
+            },
+        ]);
+        let assistant = client
+            .create_assistant(&opts)
+            .await
+            .expect("error creating assistant");
+        assert_eq!(assistant.name.as_deref(), Some("Pond Keeper"));
+        assert!(matches!(assistant.tools[0], AssistantTool::CodeInterpreter));
+
+        let requests = server.received_requests().await.expect("no requests");
+        let body: Value = serde_json::from_slice(&requests[0].body).expect("invalid body");
+        assert_eq!(
+            body["tools"],
+            json!([
+                { "type": "code_interpreter" },
+                {
+                    "type": "function",
+                    "function": {
+                        "name": "get_pond_temperature",
+                        "parameters": { "type": "object", "properties": {} }
+                    }
+                }
+            ])
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_run_with_tool_outputs() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/threads/thread-1/runs"))
+            .and(header("OpenAI-Beta", "assistants=v2"))
+            .and(body_json(json!({ "assistant_id": "asst-1" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(run_json("queued")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/threads/thread-1/runs/run-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(run_json("in_progress")))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/threads/thread-1/runs/run-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(run_json("requires_action")))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/threads/thread-1/runs/run-1/submit_tool_outputs"))
+            .and(body_json(json!({
+                "tool_outputs": [{ "tool_call_id": "call-1", "output": "18C" }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(run_json("in_progress")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/threads/thread-1/runs/run-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(run_json("completed")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/threads/thread-1/messages"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "object": "list",
+                "data": [{
+                    "id": "msg-2",
+                    "object": "thread.message",
+                    "created_at": 1699063300,
+                    "thread_id": "thread-1",
+                    "role": "assistant",
+                    "content": [{
+                        "type": "text",
+                        "text": { "value": "The pond is 18C.", "annotations": [] }
+                    }],
+                    "assistant_id": "asst-1",
+                    "run_id": "run-1",
+                    "attachments": [],
+                    "metadata": {}
+                }],
+                "first_id": "msg-2",
+                "last_id": "msg-2",
+                "has_more": false
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let poll = PollOptions {
+            initial_interval: Duration::from_millis(5),
+            ..Default::default()
+        };
+        let run = client
+            .create_run("thread-1", &CreateRunOptions::default("asst-1"))
+            .await
+            .expect("error creating run");
+
+        let run = client
+            .wait_for_run("thread-1", &run.id, &poll)
+            .await
+            .expect("error waiting for run");
+        assert_eq!(run.status, RunStatus::RequiresAction);
+        let outputs: Vec<ToolOutput> = run
+            .tool_calls()
+            .iter()
+            .map(|call| ToolOutput {
+                tool_call_id: call.id.clone(),
+                output: "18C".to_owned(),
+            })
+            .collect();
+        client
+            .submit_tool_outputs("thread-1", &run.id, &outputs)
+            .await
+            .expect("error submitting tool outputs");
+
+        let run = client
+            .wait_for_run("thread-1", &run.id, &poll)
+            .await
+            .expect("error waiting for run");
+        assert_eq!(run.status, RunStatus::Completed);
+        assert_eq!(run.usage.map(|usage| usage.total_tokens), Some(135));
+
+        let messages = client
+            .list_messages("thread-1", &ListOptions::default())
+            .await
+            .expect("error listing messages");
+        assert_eq!(messages.data[0].role, ChatRole::Assistant);
+        assert_eq!(messages.data[0].text(), "The pond is 18C.");
+    }
+}
@@ -1,5 +1,3 @@
-use serde::{Deserialize, Serialize};
-
 use crate::OpenAIClient;
 
 impl OpenAIClient {
@@ -7,7 +5,9 @@ impl OpenAIClient {
     pub async fn create_translation() {}
 }
 
+// Scaffolding for tests that haven't been written yet
 #[cfg(test)]
+#[allow(unused_imports)]
 mod tests {
     use super::*;
 }
@@ -0,0 +1,553 @@
+use core::fmt;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::Display;
+
+use reqwest::Method;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::OpenAIClient;
+
+use super::chat::{ChatCompletion, ChatOptions};
+use super::embeddings::{CreateEmbeddingsOptions, Embeddings};
+use super::files::{FilePurpose, UploadFileOptions};
+use super::list::{ListOptions, ListResponse};
+use super::poll::{Backoff, PollOptions};
+
+/// A request type the Batch API accepts, and the response each of its lines produces.
+pub trait BatchEndpoint: Serialize {
+    type Response: DeserializeOwned;
+
+    /// The endpoint as written in the batch file, e.g. `/v1/chat/completions`
+    const URL: &'static str;
+}
+
+impl BatchEndpoint for ChatOptions {
+    type Response = ChatCompletion;
+    const URL: &'static str = "/v1/chat/completions";
+}
+
+impl BatchEndpoint for CreateEmbeddingsOptions {
+    type Response = Embeddings;
+    const URL: &'static str = "/v1/embeddings";
+}
+
+#[derive(Debug, Serialize)]
+struct BatchRequestLine<'a, T> {
+    custom_id: &'a str,
+    method: &'static str,
+    url: &'static str,
+    body: &'a T,
+}
+
+/// Collects requests to one endpoint into the JSONL input file of a batch.
+#[derive(Debug)]
+pub struct BatchBuilder<T: BatchEndpoint> {
+    pub requests: Vec<(String, T)>,
+    /// Only `"24h"` is currently supported
+    pub completion_window: String,
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+impl<T: BatchEndpoint> Default for BatchBuilder<T> {
+    fn default() -> Self {
+        Self {
+            requests: vec![],
+            completion_window: "24h".to_owned(),
+            metadata: None,
+        }
+    }
+}
+
+impl<T: BatchEndpoint> BatchBuilder<T> {
+    /// Adds a request. `custom_id` must be unique within the batch; results are keyed by it, and
+    /// `to_jsonl` rejects duplicates.
+    pub fn push(&mut self, custom_id: &str, request: T) {
+        self.requests.push((custom_id.to_owned(), request));
+    }
+
+    /// Fails if two requests share a `custom_id`, since only one of their results could be kept.
+    pub fn to_jsonl(&self) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let mut custom_ids = HashSet::new();
+        let mut jsonl = String::new();
+        for (custom_id, body) in &self.requests {
+            if !custom_ids.insert(custom_id) {
+                return Err(format!("duplicate custom_id {custom_id:?} in batch").into());
+            }
+            jsonl += &serde_json::to_string(&BatchRequestLine {
+                custom_id,
+                method: "POST",
+                url: T::URL,
+                body,
+            })?;
+            jsonl.push('\n');
+        }
+        Ok(jsonl)
+    }
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateBatchOptions {
+    pub input_file_id: String,
+    pub endpoint: String,
+    pub completion_window: String,
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+impl CreateBatchOptions {
+    pub fn default(input_file_id: &str, endpoint: &str) -> Self {
+        Self {
+            input_file_id: input_file_id.to_owned(),
+            endpoint: endpoint.to_owned(),
+            completion_window: "24h".to_owned(),
+            metadata: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BatchStatus {
+    #[serde(rename = "validating")]
+    Validating,
+    #[serde(rename = "failed")]
+    Failed,
+    #[serde(rename = "in_progress")]
+    InProgress,
+    #[serde(rename = "finalizing")]
+    Finalizing,
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "expired")]
+    Expired,
+    #[serde(rename = "cancelling")]
+    Cancelling,
+    #[serde(rename = "cancelled")]
+    Cancelled,
+}
+
+impl BatchStatus {
+    /// Whether the batch has stopped and will not change status again. Expired and cancelled
+    /// batches may still have output for the requests that completed.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            BatchStatus::Failed
+                | BatchStatus::Completed
+                | BatchStatus::Expired
+                | BatchStatus::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchRequestCounts {
+    pub total: u64,
+    pub completed: u64,
+    pub failed: u64,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Batch {
+    pub id: String,
+    pub object: String,
+    pub endpoint: String,
+    pub errors: Option<Value>,
+    pub input_file_id: String,
+    pub completion_window: String,
+    pub status: BatchStatus,
+    pub output_file_id: Option<String>,
+    pub error_file_id: Option<String>,
+    pub created_at: u64,
+    pub in_progress_at: Option<u64>,
+    pub expires_at: Option<u64>,
+    pub completed_at: Option<u64>,
+    pub failed_at: Option<u64>,
+    pub expired_at: Option<u64>,
+    pub request_counts: Option<BatchRequestCounts>,
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+/// Why one request in a batch did not produce a response.
+#[derive(Debug, Clone)]
+pub struct BatchRequestError {
+    /// The HTTP status of the request, if it reached the endpoint
+    pub status_code: Option<u16>,
+    pub code: Option<String>,
+    pub message: String,
+}
+
+impl Display for BatchRequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.status_code {
+            Some(status) => write!(
+                f,
+                "batch request failed (status {status}): {}",
+                self.message
+            ),
+            None => write!(f, "batch request failed: {}", self.message),
+        }
+    }
+}
+
+impl Error for BatchRequestError {}
+
+#[derive(Debug, Deserialize)]
+struct BatchLineError {
+    code: Option<String>,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchLineResponse {
+    status_code: u16,
+    body: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchResultLine {
+    custom_id: String,
+    response: Option<BatchLineResponse>,
+    error: Option<BatchLineError>,
+}
+
+impl BatchResultLine {
+    fn into_result<R: DeserializeOwned>(self) -> Result<R, BatchRequestError> {
+        if let Some(error) = self.error {
+            return Err(BatchRequestError {
+                status_code: self.response.map(|res| res.status_code),
+                code: error.code,
+                message: error.message,
+            });
+        }
+        let Some(res) = self.response else {
+            return Err(BatchRequestError {
+                status_code: None,
+                code: None,
+                message: "no response".to_owned(),
+            });
+        };
+        if !(200..300).contains(&res.status_code) {
+            let error = &res.body["error"];
+            return Err(BatchRequestError {
+                status_code: Some(res.status_code),
+                code: error["code"].as_str().map(str::to_owned),
+                message: error["message"].as_str().unwrap_or_default().to_owned(),
+            });
+        }
+        R::deserialize(&res.body).map_err(|err| BatchRequestError {
+            status_code: Some(res.status_code),
+            code: None,
+            message: err.to_string(),
+        })
+    }
+}
+
+/// Results of a finished batch keyed by custom id. Requests with no line in the output or error
+/// file, for example because the batch failed validation, have no entry.
+pub type BatchResults<R> = HashMap<String, Result<R, BatchRequestError>>;
+
+fn parse_results<R: DeserializeOwned>(
+    jsonl: &[u8],
+    results: &mut BatchResults<R>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    for line in String::from_utf8_lossy(jsonl).lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line: BatchResultLine = serde_json::from_str(line)?;
+        results.insert(line.custom_id.clone(), line.into_result());
+    }
+    Ok(())
+}
+
+impl OpenAIClient {
+    pub async fn create_batch(
+        &self,
+        opts: &CreateBatchOptions,
+    ) -> Result<Batch, Box<dyn Error + Send + Sync>> {
+        Ok(self.post_json("/batches", opts).await?.data)
+    }
+
+    pub async fn get_batch(&self, batch_id: &str) -> Result<Batch, Box<dyn Error + Send + Sync>> {
+        Ok(self.get_json(&format!("/batches/{batch_id}")).await?.data)
+    }
+
+    pub async fn cancel_batch(
+        &self,
+        batch_id: &str,
+    ) -> Result<Batch, Box<dyn Error + Send + Sync>> {
+        let request = self.request(Method::POST, &format!("/batches/{batch_id}/cancel"));
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn list_batches(
+        &self,
+        opts: &ListOptions,
+    ) -> Result<ListResponse<Batch>, Box<dyn Error + Send + Sync>> {
+        let request = self.request(Method::GET, "/batches").query(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    /// Uploads the batch's input file and creates the batch.
+    pub async fn submit_batch<T: BatchEndpoint>(
+        &self,
+        batch: &BatchBuilder<T>,
+    ) -> Result<Batch, Box<dyn Error + Send + Sync>> {
+        let jsonl = batch.to_jsonl()?;
+        let file = self
+            .upload_file(UploadFileOptions::default(
+                "batch.jsonl",
+                FilePurpose::Batch,
+                jsonl.into_bytes().into(),
+            ))
+            .await?;
+
+        let mut opts = CreateBatchOptions::default(&file.id, T::URL);
+        opts.completion_window = batch.completion_window.clone();
+        opts.metadata = batch.metadata.clone();
+        self.create_batch(&opts).await
+    }
+
+    /// Polls a batch until it completes, fails, expires or is cancelled. Returns the finished
+    /// batch; check its `status` to see how it ended.
+    pub async fn wait_for_batch(
+        &self,
+        batch_id: &str,
+        poll: &PollOptions,
+    ) -> Result<Batch, Box<dyn Error + Send + Sync>> {
+        let mut backoff = Backoff::new(poll);
+        loop {
+            let batch = self.get_batch(batch_id).await?;
+            if batch.status.is_finished() {
+                return Ok(batch);
+            }
+            backoff.wait().await?;
+        }
+    }
+
+    /// Downloads a finished batch's output and error files and parses each line as `R`, the
+    /// response type of the batch's endpoint.
+    pub async fn get_batch_results<R: DeserializeOwned>(
+        &self,
+        batch: &Batch,
+    ) -> Result<BatchResults<R>, Box<dyn Error + Send + Sync>> {
+        let mut results = HashMap::new();
+        for file_id in [&batch.output_file_id, &batch.error_file_id]
+            .into_iter()
+            .flatten()
+        {
+            let jsonl = self.download_file(file_id).await?;
+            parse_results(&jsonl, &mut results)?;
+        }
+        Ok(results)
+    }
+
+    /// Submits a batch, waits for it to finish and returns its results.
+    pub async fn run_batch<T: BatchEndpoint>(
+        &self,
+        batch: &BatchBuilder<T>,
+        poll: &PollOptions,
+    ) -> Result<BatchResults<T::Response>, Box<dyn Error + Send + Sync>> {
+        let submitted = self.submit_batch(batch).await?;
+        let finished = self.wait_for_batch(&submitted.id, poll).await?;
+        self.get_batch_results(&finished).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::openai::chat::{ChatMessage, ChatRole};
+    use serde_json::json;
+    use std::time::Duration;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn batch_json(status: &str) -> Value {
+        json!({
+            "id": "batch-1",
+            "object": "batch",
+            "endpoint": "/v1/chat/completions",
+            "errors": null,
+            "input_file_id": "file-in",
+            "completion_window": "24h",
+            "status": status,
+            "output_file_id": if status == "completed" { json!("file-out") } else { Value::Null },
+            "error_file_id": if status == "completed" { json!("file-err") } else { Value::Null },
+            "created_at": 1711471533,
+            "request_counts": { "total": 3, "completed": 1, "failed": 2 }
+        })
+    }
+
+    fn chat_opts(content: &str) -> ChatOptions {
+        ChatOptions::default(
+            "gpt-3.5-turbo",
+            vec![ChatMessage::new(ChatRole::User, content)],
+            20,
+        )
+    }
+
+    #[test]
+    fn test_batch_jsonl() {
+        let mut batch = BatchBuilder::default();
+        batch.push(
+            "doc-1",
+            CreateEmbeddingsOptions::default("text-embedding-3-small", vec!["toad".to_owned()]),
+        );
+        batch.push(
+            "doc-2",
+            CreateEmbeddingsOptions::default("text-embedding-3-small", vec!["frog".to_owned()]),
+        );
+
+        let jsonl = batch.to_jsonl().expect("error writing batch");
+        let lines: Vec<Value> = jsonl
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("invalid line"))
+            .collect();
+        assert_eq!(
+            lines[1],
+            json!({
+                "custom_id": "doc-2",
+                "method": "POST",
+                "url": "/v1/embeddings",
+                "body": { "model": "text-embedding-3-small", "input": ["frog"] }
+            })
+        );
+
+        batch.push(
+            "doc-1",
+            CreateEmbeddingsOptions::default("text-embedding-3-small", vec!["newt".to_owned()]),
+        );
+        let err = batch
+            .to_jsonl()
+            .expect_err("duplicate custom_id was written");
+        assert_eq!(err.to_string(), "duplicate custom_id \"doc-1\" in batch");
+    }
+
+    #[tokio::test]
+    pub async fn test_run_batch() {
+        let output = json!({
+            "id": "batch_req_1",
+            "custom_id": "greeting",
+            "response": {
+                "status_code": 200,
+                "request_id": "req-1",
+                "body": {
+                    "id": "chatcmpl-1",
+                    "object": "chat.completion",
+                    "created": 1711475054,
+                    "model": "gpt-3.5-turbo-0125",
+                    "choices": [{
+                        "index": 0,
+                        "message": { "role": "assistant", "content": "Ribbit." },
+                        "finish_reason": "stop"
+                    }],
+                    "usage": { "prompt_tokens": 9, "completion_tokens": 3, "total_tokens": 12 }
+                }
+            },
+            "error": null
+        });
+        let errors = [
+            json!({
+                "id": "batch_req_2",
+                "custom_id": "too-long",
+                "response": {
+                    "status_code": 400,
+                    "request_id": "req-2",
+                    "body": { "error": { "message": "max_tokens is too large", "code": null } }
+                },
+                "error": null
+            }),
+            json!({
+                "id": "batch_req_3",
+                "custom_id": "expired",
+                "response": null,
+                "error": { "code": "batch_expired", "message": "This request could not be executed" }
+            }),
+        ];
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/files"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "file-in",
+                "object": "file",
+                "bytes": 512,
+                "created_at": 1711471533,
+                "filename": "batch.jsonl",
+                "purpose": "batch"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/batches"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(batch_json("validating")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/batches/batch-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(batch_json("in_progress")))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/batches/batch-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(batch_json("completed")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/files/file-out/content"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!("{output}\n")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/files/file-err/content"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(format!("{}\n{}\n", errors[0], errors[1])),
+            )
+            .mount(&server)
+            .await;
+
+        let mut batch = BatchBuilder::default();
+        batch.push("greeting", chat_opts("say hi"));
+        batch.push("too-long", chat_opts("write a novel"));
+        batch.push("expired", chat_opts("say bye"));
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let poll = PollOptions {
+            initial_interval: Duration::from_millis(5),
+            ..Default::default()
+        };
+        let results = client
+            .run_batch(&batch, &poll)
+            .await
+            .expect("error running batch");
+
+        let completion = results["greeting"].as_ref().expect("request failed");
+        assert_eq!(completion.choices[0].message.content, "Ribbit.");
+        let too_long = results["too-long"].as_ref().expect_err("request succeeded");
+        assert_eq!(too_long.status_code, Some(400));
+        assert_eq!(too_long.message, "max_tokens is too large");
+        let expired = results["expired"].as_ref().expect_err("request succeeded");
+        assert_eq!(expired.code.as_deref(), Some("batch_expired"));
+
+        let requests = server.received_requests().await.expect("no requests");
+        let upload = String::from_utf8_lossy(&requests[0].body);
+        assert!(upload.contains("name=\"purpose\"\r\n\r\nbatch\r\n"));
+        assert!(upload.contains("\"custom_id\":\"too-long\""));
+        let create: Value = serde_json::from_slice(&requests[1].body).expect("invalid body");
+        assert_eq!(
+            create,
+            json!({
+                "input_file_id": "file-in",
+                "endpoint": "/v1/chat/completions",
+                "completion_window": "24h"
+            })
+        );
+    }
+}
@@ -0,0 +1,479 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::pricing::Pricing;
+use super::usage::Usage;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BudgetLimit {
+    /// US dollars, priced with the manager's [`Pricing`]
+    Dollars(f64),
+    Tokens(u64),
+}
+
+impl Display for BudgetLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetLimit::Dollars(dollars) => write!(f, "${dollars:.2}"),
+            BudgetLimit::Tokens(tokens) => write!(f, "{tokens} tokens"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BudgetScope {
+    /// Every request made through the client
+    All,
+    /// Requests made through a client tagged with [`crate::OpenAIClient::with_tag`]
+    Tag(String),
+}
+
+impl BudgetScope {
+    fn applies_to(&self, tag: Option<&str>) -> bool {
+        match self {
+            BudgetScope::All => true,
+            BudgetScope::Tag(scope) => tag == Some(scope.as_str()),
+        }
+    }
+}
+
+/// A spending limit. A request that would take spend past `hard_limit` is refused with a
+/// [`BudgetExceeded`] error; crossing `soft_limit` only fires the manager's alert callback.
+#[derive(Debug, Clone)]
+pub struct Budget {
+    pub scope: BudgetScope,
+    /// Spend is counted over a rolling window of this length, or forever if `None`
+    pub window: Option<Duration>,
+    pub hard_limit: Option<BudgetLimit>,
+    pub soft_limit: Option<BudgetLimit>,
+}
+
+impl Budget {
+    pub fn hard(scope: BudgetScope, limit: BudgetLimit, window: Option<Duration>) -> Self {
+        Self {
+            scope,
+            window,
+            hard_limit: Some(limit),
+            soft_limit: None,
+        }
+    }
+
+    pub fn soft(scope: BudgetScope, limit: BudgetLimit, window: Option<Duration>) -> Self {
+        Self {
+            scope,
+            window,
+            hard_limit: None,
+            soft_limit: Some(limit),
+        }
+    }
+}
+
+/// Returned (boxed) by requests that were refused because they could take a budget past its hard
+/// limit. No request is sent when this is returned.
+#[derive(Debug, Clone)]
+pub struct BudgetExceeded {
+    pub budget: Budget,
+    pub limit: BudgetLimit,
+    pub spent_tokens: u64,
+    pub spent_dollars: f64,
+    /// Held for requests that have been checked but not yet recorded
+    pub reserved_tokens: u64,
+    pub reserved_dollars: f64,
+    pub estimated_tokens: u64,
+    pub estimated_dollars: f64,
+}
+
+impl Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "request refused: estimated {} tokens (${:.4}) would exceed the budget of {} for {:?} \
+             ({} tokens, ${:.4} already spent, {} tokens, ${:.4} reserved)",
+            self.estimated_tokens,
+            self.estimated_dollars,
+            self.limit,
+            self.budget.scope,
+            self.spent_tokens,
+            self.spent_dollars,
+            self.reserved_tokens,
+            self.reserved_dollars
+        )
+    }
+}
+
+impl Error for BudgetExceeded {}
+
+/// Passed to the alert callback when spend first crosses a budget's soft limit.
+#[derive(Debug, Clone)]
+pub struct BudgetAlert {
+    pub budget: Budget,
+    pub limit: BudgetLimit,
+    pub spent_tokens: u64,
+    pub spent_dollars: f64,
+}
+
+/// The estimated cost of a request, held against every budget that applies to its tag from
+/// [`BudgetManager::check`] until the request is recorded or released. Reservations don't
+/// expire, so every one must be passed to [`BudgetManager::record_reserved`] or
+/// [`BudgetManager::release`].
+#[must_use]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetReservation {
+    pub tag: Option<String>,
+    pub tokens: u64,
+    pub dollars: f64,
+}
+
+#[derive(Debug, Default)]
+struct BudgetState {
+    spend: VecDeque<(Instant, u64, f64)>,
+    tokens: u64,
+    dollars: f64,
+    reserved_tokens: u64,
+    reserved_dollars: f64,
+    alerted: bool,
+}
+
+impl BudgetState {
+    fn expire(&mut self, window: Option<Duration>, now: Instant) {
+        let Some(window) = window else {
+            return;
+        };
+        while let Some(&(at, tokens, dollars)) = self.spend.front() {
+            if now.duration_since(at) < window {
+                break;
+            }
+            self.spend.pop_front();
+            self.tokens -= tokens;
+            self.dollars -= dollars;
+        }
+    }
+
+    fn unreserve(&mut self, reservation: &BudgetReservation) {
+        self.reserved_tokens = self.reserved_tokens.saturating_sub(reservation.tokens);
+        self.reserved_dollars = (self.reserved_dollars - reservation.dollars).max(0.0);
+    }
+
+    fn exceeds(&self, limit: BudgetLimit, tokens: u64, dollars: f64) -> bool {
+        match limit {
+            BudgetLimit::Dollars(max) => self.dollars + dollars > max,
+            BudgetLimit::Tokens(max) => self.tokens + tokens > max,
+        }
+    }
+}
+
+type AlertCallback = Box<dyn Fn(&BudgetAlert) + Send + Sync>;
+
+/// Enforces a set of [`Budget`]s. Attach one to [`crate::OpenAIClient::budgets`] and chat,
+/// completion and embedding requests are estimated before they are sent and recorded after.
+pub struct BudgetManager {
+    budgets: Vec<Budget>,
+    pricing: Pricing,
+    state: Mutex<Vec<BudgetState>>,
+    on_alert: Option<AlertCallback>,
+}
+
+impl fmt::Debug for BudgetManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BudgetManager")
+            .field("budgets", &self.budgets)
+            .field("pricing", &self.pricing)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BudgetManager {
+    pub fn new(budgets: Vec<Budget>, pricing: Pricing) -> Self {
+        let state = budgets.iter().map(|_| BudgetState::default()).collect();
+        Self {
+            budgets,
+            pricing,
+            state: Mutex::new(state),
+            on_alert: None,
+        }
+    }
+
+    /// Calls `callback` whenever spend first crosses a budget's soft limit. It fires again only
+    /// after spend in the window has dropped back under the limit.
+    pub fn with_alert(mut self, callback: impl Fn(&BudgetAlert) + Send + Sync + 'static) -> Self {
+        self.on_alert = Some(Box::new(callback));
+        self
+    }
+
+    pub fn budgets(&self) -> &[Budget] {
+        &self.budgets
+    }
+
+    /// Checks a request that will use up to `prompt_tokens + max_tokens` tokens of `model`
+    /// against every hard limit that applies to `tag`, counting what earlier requests still in
+    /// flight have reserved. If it fits, the estimate is reserved in the same step, so concurrent
+    /// requests can't all pass the check and then overshoot the limit together.
+    // Returned unboxed so callers can read the limits without downcasting
+    #[allow(clippy::result_large_err)]
+    pub fn check(
+        &self,
+        tag: Option<&str>,
+        model: &str,
+        prompt_tokens: u64,
+        max_tokens: u64,
+    ) -> Result<BudgetReservation, BudgetExceeded> {
+        let estimated_tokens = prompt_tokens + max_tokens;
+        let estimated_dollars = self.pricing.cost(model, prompt_tokens, max_tokens);
+        let now = Instant::now();
+
+        let mut state = self.state.lock().expect("budget lock poisoned");
+        for (budget, state) in self.budgets.iter().zip(state.iter_mut()) {
+            if !budget.scope.applies_to(tag) {
+                continue;
+            }
+            state.expire(budget.window, now);
+            if let Some(limit) = budget.hard_limit {
+                if state.exceeds(
+                    limit,
+                    state.reserved_tokens + estimated_tokens,
+                    state.reserved_dollars + estimated_dollars,
+                ) {
+                    return Err(BudgetExceeded {
+                        budget: budget.clone(),
+                        limit,
+                        spent_tokens: state.tokens,
+                        spent_dollars: state.dollars,
+                        reserved_tokens: state.reserved_tokens,
+                        reserved_dollars: state.reserved_dollars,
+                        estimated_tokens,
+                        estimated_dollars,
+                    });
+                }
+            }
+        }
+
+        for (budget, state) in self.budgets.iter().zip(state.iter_mut()) {
+            if budget.scope.applies_to(tag) {
+                state.reserved_tokens += estimated_tokens;
+                state.reserved_dollars += estimated_dollars;
+            }
+        }
+        Ok(BudgetReservation {
+            tag: tag.map(str::to_owned),
+            tokens: estimated_tokens,
+            dollars: estimated_dollars,
+        })
+    }
+
+    /// Drops a reservation without recording any spend, for a request that failed.
+    pub fn release(&self, reservation: BudgetReservation) {
+        let mut state = self.state.lock().expect("budget lock poisoned");
+        for (budget, state) in self.budgets.iter().zip(state.iter_mut()) {
+            if budget.scope.applies_to(reservation.tag.as_deref()) {
+                state.unreserve(&reservation);
+            }
+        }
+    }
+
+    /// Records spend that wasn't reserved with [`BudgetManager::check`].
+    pub fn record(&self, tag: Option<&str>, model: &str, usage: &Usage) {
+        self.record_usage(tag, None, model, usage);
+    }
+
+    /// Replaces a reservation with what the request actually cost.
+    pub fn record_reserved(&self, reservation: BudgetReservation, model: &str, usage: &Usage) {
+        let tag = reservation.tag.clone();
+        self.record_usage(tag.as_deref(), Some(&reservation), model, usage);
+    }
+
+    fn record_usage(
+        &self,
+        tag: Option<&str>,
+        reservation: Option<&BudgetReservation>,
+        model: &str,
+        usage: &Usage,
+    ) {
+        let completion_tokens = usage.completion_tokens.unwrap_or(0);
+        let tokens = usage.prompt_tokens + completion_tokens;
+        let dollars = self
+            .pricing
+            .cost(model, usage.prompt_tokens, completion_tokens);
+        let now = Instant::now();
+
+        let mut alerts = vec![];
+        {
+            let mut state = self.state.lock().expect("budget lock poisoned");
+            for (budget, state) in self.budgets.iter().zip(state.iter_mut()) {
+                if !budget.scope.applies_to(tag) {
+                    continue;
+                }
+                state.expire(budget.window, now);
+                if let Some(reservation) = reservation {
+                    state.unreserve(reservation);
+                }
+                // Lifetime budgets only need the running totals
+                if budget.window.is_some() {
+                    state.spend.push_back((now, tokens, dollars));
+                }
+                state.tokens += tokens;
+                state.dollars += dollars;
+
+                if let Some(limit) = budget.soft_limit {
+                    let over = state.exceeds(limit, 0, 0.0);
+                    if over && !state.alerted {
+                        alerts.push(BudgetAlert {
+                            budget: budget.clone(),
+                            limit,
+                            spent_tokens: state.tokens,
+                            spent_dollars: state.dollars,
+                        });
+                    }
+                    state.alerted = over;
+                }
+            }
+        }
+
+        if let Some(on_alert) = &self.on_alert {
+            for alert in &alerts {
+                on_alert(alert);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::openai::pricing::ModelPricing;
+    use std::sync::{atomic::AtomicUsize, atomic::Ordering, Arc, Barrier};
+
+    fn usage(prompt_tokens: u64, completion_tokens: u64) -> Usage {
+        Usage {
+            prompt_tokens,
+            completion_tokens: Some(completion_tokens),
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    }
+
+    #[test]
+    fn test_hard_limit() {
+        let mut pricing = Pricing::empty();
+        pricing.set("model", ModelPricing::new(1.0, 1.0));
+        let budgets = BudgetManager::new(
+            vec![Budget::hard(
+                BudgetScope::Tag("staging".to_owned()),
+                BudgetLimit::Dollars(3.0),
+                None,
+            )],
+            pricing,
+        );
+
+        let reservation = budgets
+            .check(Some("staging"), "model", 1000, 1000)
+            .expect("first request should fit in the budget");
+        budgets.record_reserved(reservation, "model", &usage(1000, 1000));
+
+        let err = budgets
+            .check(Some("staging"), "model", 1000, 1000)
+            .expect_err("second request should exceed the budget");
+        assert_eq!(err.spent_dollars, 2.0);
+        assert_eq!(err.estimated_dollars, 2.0);
+
+        // other tags are not affected
+        let _reservation = budgets
+            .check(Some("production"), "model", 1000, 1000)
+            .expect("untagged budget should not apply");
+    }
+
+    #[test]
+    fn test_window_expiry() {
+        let budgets = BudgetManager::new(
+            vec![Budget::hard(
+                BudgetScope::All,
+                BudgetLimit::Tokens(100),
+                Some(Duration::from_millis(20)),
+            )],
+            Pricing::empty(),
+        );
+        budgets.record(None, "model", &usage(80, 0));
+        assert!(budgets.check(None, "model", 10, 20).is_err());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(budgets.check(None, "model", 10, 20).is_ok());
+    }
+
+    #[test]
+    fn test_soft_limit_alert() {
+        let alerts = Arc::new(AtomicUsize::new(0));
+        let counter = alerts.clone();
+        let budgets = BudgetManager::new(
+            vec![Budget::soft(
+                BudgetScope::All,
+                BudgetLimit::Tokens(100),
+                None,
+            )],
+            Pricing::empty(),
+        )
+        .with_alert(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        budgets.record(None, "model", &usage(60, 0));
+        assert_eq!(alerts.load(Ordering::SeqCst), 0);
+        budgets.record(None, "model", &usage(60, 0));
+        assert_eq!(alerts.load(Ordering::SeqCst), 1);
+        budgets.record(None, "model", &usage(60, 0));
+        assert_eq!(alerts.load(Ordering::SeqCst), 1);
+
+        // soft limits never refuse requests
+        assert!(budgets.check(None, "model", 1000, 0).is_ok());
+        // lifetime budgets keep totals, not a log of every request
+        assert!(budgets.state.lock().unwrap()[0].spend.is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_checks_reserve() {
+        let budgets = Arc::new(BudgetManager::new(
+            vec![Budget::hard(
+                BudgetScope::All,
+                BudgetLimit::Tokens(100),
+                None,
+            )],
+            Pricing::empty(),
+        ));
+        let barrier = Arc::new(Barrier::new(10));
+        let threads: Vec<_> = (0..10)
+            .map(|_| {
+                let budgets = budgets.clone();
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    budgets.check(None, "model", 10, 20).ok()
+                })
+            })
+            .collect();
+        let reservations: Vec<_> = threads
+            .into_iter()
+            .filter_map(|thread| thread.join().expect("check panicked"))
+            .collect();
+        assert_eq!(reservations.len(), 3);
+
+        let err = budgets
+            .check(None, "model", 11, 0)
+            .expect_err("reserved tokens should count against the limit");
+        assert_eq!((err.spent_tokens, err.reserved_tokens), (0, 90));
+
+        // recording replaces the estimate with the real usage, and releasing frees it
+        let mut reservations = reservations.into_iter();
+        budgets.record_reserved(reservations.next().unwrap(), "model", &usage(10, 5));
+        budgets.release(reservations.next().unwrap());
+        let err = budgets
+            .check(None, "model", 60, 0)
+            .expect_err("request should exceed the budget");
+        assert_eq!((err.spent_tokens, err.reserved_tokens), (15, 30));
+        let reservation = budgets
+            .check(None, "model", 55, 0)
+            .expect("request should fit once the estimates are settled");
+        assert_eq!(reservation.tokens, 55);
+    }
+}
@@ -6,9 +6,11 @@ use serde_json::Value;
 
 use crate::OpenAIClient;
 
+use super::metadata::ApiResponse;
+use super::tokens::estimate_tokens;
 use super::usage::Usage;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum ChatRole {
     #[serde(rename = "system")]
     System,
@@ -18,19 +20,59 @@ pub enum ChatRole {
     Assistant,
     #[serde(rename = "function")]
     Function,
+    #[serde(rename = "tool")]
+    Tool,
 }
 
 #[serde_with::skip_serializing_none]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
-    role: ChatRole,
-    content: String,
-    name: Option<String>,
-    function_call: Option<Value>,
+    pub role: ChatRole,
+    /// May be `None` for an assistant message that makes a function or tool call
+    pub content: Option<String>,
+    pub name: Option<String>,
+    pub function_call: Option<Value>,
+    pub tool_calls: Option<Value>,
+    /// The call a `Tool` message answers
+    pub tool_call_id: Option<String>,
+}
+
+impl ChatMessage {
+    pub fn new(role: ChatRole, content: &str) -> Self {
+        Self {
+            role,
+            content: Some(content.to_owned()),
+            name: None,
+            function_call: None,
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+}
+
+/// An estimate of the tokens `messages` take up in a prompt, including the per-message overhead
+/// of the chat format.
+pub fn estimate_message_tokens(messages: &[ChatMessage]) -> u64 {
+    let tokens: u64 = messages
+        .iter()
+        .map(|message| {
+            4 + message.content.as_deref().map_or(0, estimate_tokens)
+                + message.name.as_deref().map_or(0, estimate_tokens)
+                + message
+                    .function_call
+                    .as_ref()
+                    .map_or(0, |call| estimate_tokens(&call.to_string()))
+                + message
+                    .tool_calls
+                    .as_ref()
+                    .map_or(0, |calls| estimate_tokens(&calls.to_string()))
+        })
+        .sum();
+    tokens + 3
 }
 
 #[serde_with::skip_serializing_none]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatFunction {
     pub name: String,
     pub description: Option<String>,
@@ -75,22 +117,34 @@ impl ChatOptions {
             user: None,
         }
     }
+
+    /// An estimate of the tokens in the prompt, including the per-message overhead of the chat
+    /// format and any function definitions.
+    pub fn estimated_prompt_tokens(&self) -> u64 {
+        let messages = estimate_message_tokens(&self.messages);
+        let functions = self
+            .functions
+            .as_ref()
+            .and_then(|functions| serde_json::to_string(functions).ok())
+            .map_or(0, |functions| estimate_tokens(&functions));
+        messages + functions
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatResponseMessage {
     pub role: String,
     pub content: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatResponseChoice {
     pub index: u64,
     pub message: ChatResponseMessage,
     pub finish_reason: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatCompletion {
     pub id: String,
     pub object: String,
@@ -105,17 +159,41 @@ impl OpenAIClient {
         &self,
         opts: &ChatOptions,
     ) -> Result<ChatCompletion, Box<dyn Error + Send + Sync>> {
-        let uri = self.base_uri.clone() + "/chat/completions";
-        let api_key = &self.api_key;
-        let res = self
-            .client
-            .post(&uri)
-            .header("Authorization", format!("Bearer {api_key}"))
-            .json(&opts)
-            .send()
+        Ok(self.get_chat_completion_with_metadata(opts).await?.data)
+    }
+
+    pub async fn get_chat_completion_with_metadata(
+        &self,
+        opts: &ChatOptions,
+    ) -> Result<ApiResponse<ChatCompletion>, Box<dyn Error + Send + Sync>> {
+        if let Some(content) = opts
+            .messages
+            .iter()
+            .rev()
+            .find(|message| message.role == ChatRole::User)
+            .and_then(|message| message.content.as_deref())
+        {
+            self.moderate_input(content).await?;
+        }
+
+        let prompt_tokens = opts.estimated_prompt_tokens();
+        let max_tokens = opts.max_tokens * opts.n.unwrap_or(1) as u64;
+        let reserved = self.check_budget(&opts.model, prompt_tokens, max_tokens)?;
+        self.wait_for_rate_limit(&opts.model, prompt_tokens + max_tokens)
+            .await;
+
+        let mut res: ApiResponse<ChatCompletion> = self
+            .post_json_rate_limited(&opts.model, "/chat/completions", opts)
             .await?;
-        let completion = res.json().await?;
-        Ok(completion)
+        self.record_reserved_usage(
+            reserved,
+            &res.data.model,
+            opts.user.as_deref(),
+            &res.data.usage,
+        );
+
+        res.data = self.moderate_outputs(res.data).await?;
+        Ok(res)
     }
 }
 
@@ -124,7 +202,10 @@ mod tests {
 
     use super::*;
 
-    use std::{env, sync::Once};
+    use crate::openai::usage::UsageTracker;
+    use std::{env, sync::Arc, sync::Once};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
 
     static INIT: Once = Once::new();
 
@@ -142,8 +223,10 @@ mod tests {
         let x = ChatMessage {
             role: ChatRole::System,
             name: None,
-            content: "you are a helpful assistant".to_owned(),
+            content: Some("you are a helpful assistant".to_owned()),
             function_call: None,
+            tool_calls: None,
+            tool_call_id: None,
         };
 
         println!("{:#?}", x);
@@ -153,8 +236,10 @@ mod tests {
                 vec![ChatMessage {
                     role: ChatRole::System,
                     name: None,
-                    content: "you are a helpful assistant".to_owned(),
+                    content: Some("you are a helpful assistant".to_owned()),
                     function_call: None,
+                    tool_calls: None,
+                    tool_call_id: None,
                 }],
                 20,
             ))
@@ -162,4 +247,65 @@ mod tests {
             .expect("error fetching chat completion");
         println!("{:#?}", _completion);
     }
+
+    #[tokio::test]
+    pub async fn test_chat_completion_with_metadata() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("openai-processing-ms", "250")
+                    .set_body_json(serde_json::json!({
+                        "id": "chatcmpl-1",
+                        "object": "chat.completion",
+                        "created": 1690000000,
+                        "model": "gpt-3.5-turbo-0613",
+                        "system_fingerprint": "fp_1",
+                        "choices": [{
+                            "index": 0,
+                            "message": { "role": "assistant", "content": "Hello!" },
+                            "finish_reason": "stop"
+                        }],
+                        "usage": { "prompt_tokens": 9, "completion_tokens": 3, "total_tokens": 12 }
+                    })),
+            )
+            .mount(&server)
+            .await;
+
+        let mut client = OpenAIClient::new("test-key", &server.uri());
+        let tracker = Arc::new(UsageTracker::default());
+        client.usage_tracker = Some(tracker.clone());
+
+        let mut opts = ChatOptions::default(
+            "gpt-3.5-turbo",
+            vec![ChatMessage {
+                role: ChatRole::User,
+                name: None,
+                content: Some("hi".to_owned()),
+                function_call: None,
+                tool_calls: None,
+                tool_call_id: None,
+            }],
+            20,
+        );
+        opts.user = Some("jenny".to_owned());
+        let res = client
+            .with_tag("greetings")
+            .get_chat_completion_with_metadata(&opts)
+            .await
+            .expect("error fetching chat completion");
+
+        assert_eq!(res.data.choices[0].message.content, "Hello!");
+        assert_eq!(res.raw["system_fingerprint"], "fp_1");
+        assert_eq!(
+            res.metadata.processing_time,
+            Some(std::time::Duration::from_millis(250))
+        );
+
+        let usage = tracker.snapshot();
+        assert_eq!(usage.by_tag["greetings"].prompt_tokens, 9);
+        assert_eq!(usage.by_user["jenny"].completion_tokens, 3);
+        assert_eq!(usage.by_model["gpt-3.5-turbo-0613"].requests, 1);
+    }
 }
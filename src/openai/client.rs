@@ -1,9 +1,29 @@
-use reqwest::Client;
+use std::error::Error;
+use std::sync::Arc;
 
+use reqwest::{header::HeaderMap, multipart, Client, Method, RequestBuilder};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use super::budget::{BudgetManager, BudgetReservation};
+use super::error::ApiError;
+use super::metadata::{ApiResponse, ResponseMetadata};
+use super::moderations::ModerationGate;
+use super::rate_limit::RateLimiter;
+use super::usage::{Usage, UsageTracker};
+
+#[derive(Clone)]
 pub struct OpenAIClient {
     pub api_key: String,
     pub base_uri: String,
     pub client: Client,
+    /// Attributed to every request made through this client in usage reports
+    pub tag: Option<String>,
+    pub usage_tracker: Option<Arc<UsageTracker>>,
+    pub budgets: Option<Arc<BudgetManager>>,
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// Runs chat completions through moderation when set
+    pub moderation: Option<ModerationGate>,
 }
 
 impl OpenAIClient {
@@ -14,6 +34,365 @@ impl OpenAIClient {
             api_key: api_key.to_string(),
             base_uri: base_uri.to_string(),
             client: Client::new(),
+            tag: None,
+            usage_tracker: None,
+            budgets: None,
+            rate_limiter: None,
+            moderation: None,
+        }
+    }
+
+    /// A copy of this client that attributes its requests to `tag`. The copy shares the
+    /// underlying connection pool and usage tracker.
+    pub fn with_tag(&self, tag: &str) -> Self {
+        Self {
+            tag: Some(tag.to_owned()),
+            ..self.clone()
+        }
+    }
+
+    /// A copy of this client that runs its chat completions through `gate`.
+    pub fn with_moderation(&self, gate: ModerationGate) -> Self {
+        Self {
+            moderation: Some(gate),
+            ..self.clone()
+        }
+    }
+
+    /// A copy of this client that skips the moderation gate.
+    pub fn without_moderation(&self) -> Self {
+        Self {
+            moderation: None,
+            ..self.clone()
+        }
+    }
+
+    /// A request to `path` (relative to `base_uri`) with the API key already attached. Use this
+    /// with [`OpenAIClient::send_json`] to reach endpoints this crate does not cover yet.
+    pub fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let uri = self.base_uri.clone() + path;
+        let api_key = &self.api_key;
+        self.client
+            .request(method, &uri)
+            .header("Authorization", format!("Bearer {api_key}"))
+    }
+
+    /// Sends `request`, returning the response unread if it succeeded and an [`ApiError`] if it
+    /// did not. Use this for endpoints that do not return JSON.
+    pub async fn send(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<reqwest::Response, Box<dyn Error + Send + Sync>> {
+        let res = request.send().await?;
+        if res.status().is_success() {
+            return Ok(res);
+        }
+        let metadata = ResponseMetadata::new(res.status().as_u16(), res.headers());
+        let body = res.bytes().await?;
+        Err(Box::new(ApiError::new(metadata, &body)))
+    }
+
+    /// Sends `request` and parses the response body as `T`, keeping the raw JSON alongside it.
+    /// Non-success responses are returned as an [`ApiError`].
+    pub async fn send_json<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<ApiResponse<T>, Box<dyn Error + Send + Sync>> {
+        let res = self.send(request).await?;
+        let metadata = ResponseMetadata::new(res.status().as_u16(), res.headers());
+        let body = res.bytes().await?;
+
+        let raw: Value = serde_json::from_slice(&body)?;
+        let data = T::deserialize(&raw)?;
+        Ok(ApiResponse {
+            data,
+            raw,
+            metadata,
+        })
+    }
+
+    pub async fn get_json<T: DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<ApiResponse<T>, Box<dyn Error + Send + Sync>> {
+        self.send_json(self.request(Method::GET, path)).await
+    }
+
+    pub async fn post_json<B: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<ApiResponse<T>, Box<dyn Error + Send + Sync>> {
+        self.send_json(self.request(Method::POST, path).json(body))
+            .await
+    }
+
+    pub async fn post_multipart<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        form: multipart::Form,
+    ) -> Result<ApiResponse<T>, Box<dyn Error + Send + Sync>> {
+        self.send_json(self.request(Method::POST, path).multipart(form))
+            .await
+    }
+
+    pub async fn delete_json<T: DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<ApiResponse<T>, Box<dyn Error + Send + Sync>> {
+        self.send_json(self.request(Method::DELETE, path)).await
+    }
+
+    /// Posts a request for `model`, then corrects the rate limiter from the response headers.
+    /// Failed responses count too, so a 429 slows down the requests after it.
+    pub(crate) async fn post_json_rate_limited<B: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        model: &str,
+        path: &str,
+        body: &B,
+    ) -> Result<ApiResponse<T>, Box<dyn Error + Send + Sync>> {
+        let res = self.post_json(path, body).await;
+        match &res {
+            Ok(res) => self.update_rate_limit(model, &res.metadata.headers),
+            Err(err) => {
+                if let Some(err) = err.downcast_ref::<ApiError>() {
+                    self.update_rate_limit(model, &err.metadata.headers);
+                }
+            }
+        }
+        res
+    }
+
+    /// Like [`OpenAIClient::post_json_rate_limited`], for requests whose body is read later, such
+    /// as streams.
+    pub(crate) async fn send_rate_limited(
+        &self,
+        model: &str,
+        request: RequestBuilder,
+    ) -> Result<reqwest::Response, Box<dyn Error + Send + Sync>> {
+        let res = self.send(request).await;
+        match &res {
+            Ok(res) => self.update_rate_limit(model, res.headers()),
+            Err(err) => {
+                if let Some(err) = err.downcast_ref::<ApiError>() {
+                    self.update_rate_limit(model, &err.metadata.headers);
+                }
+            }
+        }
+        res
+    }
+
+    /// Reserves the estimated cost of a request against the client's budgets until it is passed
+    /// to `record_reserved_usage`.
+    pub(crate) fn check_budget(
+        &self,
+        model: &str,
+        prompt_tokens: u64,
+        max_tokens: u64,
+    ) -> Result<ReservedBudget, Box<dyn Error + Send + Sync>> {
+        let hold = match &self.budgets {
+            Some(budgets) => Some((
+                budgets.clone(),
+                budgets.check(self.tag.as_deref(), model, prompt_tokens, max_tokens)?,
+            )),
+            None => None,
+        };
+        Ok(ReservedBudget { hold })
+    }
+
+    pub(crate) async fn wait_for_rate_limit(&self, model: &str, tokens: u64) {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire(model, tokens).await;
+        }
+    }
+
+    pub(crate) fn update_rate_limit(&self, model: &str, headers: &HeaderMap) {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.update_from_headers(model, headers);
+        }
+    }
+
+    pub(crate) fn record_reserved_usage(
+        &self,
+        mut reserved: ReservedBudget,
+        model: &str,
+        user: Option<&str>,
+        usage: &Usage,
+    ) {
+        if let Some(tracker) = &self.usage_tracker {
+            tracker.record(model, self.tag.as_deref(), user, usage);
+        }
+        if let Some((budgets, reservation)) = reserved.hold.take() {
+            budgets.record_reserved(reservation, model, usage);
+        }
+    }
+}
+
+/// A budget reservation for a request in flight. Dropping it without recording usage, because the
+/// request failed or was cancelled, releases the reservation.
+pub(crate) struct ReservedBudget {
+    hold: Option<(Arc<BudgetManager>, BudgetReservation)>,
+}
+
+impl Drop for ReservedBudget {
+    fn drop(&mut self) {
+        if let Some((budgets, reservation)) = self.hold.take() {
+            budgets.release(reservation);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::Deserialize;
+    use serde_json::json;
+    use std::time::{Duration, Instant};
+
+    use super::super::budget::{Budget, BudgetLimit, BudgetScope};
+    use super::super::embeddings::CreateEmbeddingsOptions;
+    use super::super::pricing::Pricing;
+    use super::super::rate_limit::RateLimits;
+    use wiremock::matchers::{body_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[derive(Debug, Deserialize)]
+    struct Widget {
+        id: String,
+    }
+
+    #[tokio::test]
+    async fn test_post_json_keeps_raw_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/widgets"))
+            .and(header("Authorization", "Bearer test-key"))
+            .and(body_json(json!({ "name": "toad" })))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("x-request-id", "req_1")
+                    .set_body_json(json!({ "id": "widget_1", "colour": "green" })),
+            )
+            .mount(&server)
+            .await;
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let res: ApiResponse<Widget> = client
+            .post_json("/widgets", &json!({ "name": "toad" }))
+            .await
+            .expect("error posting widget");
+
+        assert_eq!(res.data.id, "widget_1");
+        assert_eq!(res.raw["colour"], "green");
+        assert_eq!(res.metadata.request_id.as_deref(), Some("req_1"));
+    }
+
+    #[tokio::test]
+    async fn test_api_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/widgets/missing"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "error": {
+                    "message": "No such widget",
+                    "type": "invalid_request_error",
+                    "param": null,
+                    "code": "widget_not_found"
+                }
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let err = client
+            .get_json::<Value>("/widgets/missing")
+            .await
+            .expect_err("request should fail");
+        let err = err
+            .downcast_ref::<ApiError>()
+            .expect("expected an ApiError");
+
+        assert_eq!(err.status(), 404);
+        assert_eq!(err.error.code.as_deref(), Some("widget_not_found"));
+        assert_eq!(err.error.message, "No such widget");
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_learns_from_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .respond_with(
+                ResponseTemplate::new(429)
+                    .insert_header("x-ratelimit-remaining-requests", "0")
+                    .set_body_json(json!({
+                        "error": { "message": "Rate limit reached", "type": "requests" }
+                    })),
+            )
+            .mount(&server)
+            .await;
+
+        let mut limiter = RateLimiter::new();
+        limiter.set("text-embedding-ada-002", RateLimits::new(600, 1_000_000));
+        let limiter = Arc::new(limiter);
+        let mut client = OpenAIClient::new("test-key", &server.uri());
+        client.rate_limiter = Some(limiter.clone());
+
+        client
+            .wait_for_rate_limit("text-embedding-ada-002", 1)
+            .await;
+        let err = client
+            .post_json_rate_limited::<_, Value>("text-embedding-ada-002", "/embeddings", &json!({}))
+            .await
+            .expect_err("a 429 succeeded");
+        assert_eq!(
+            err.downcast_ref::<ApiError>().map(ApiError::status),
+            Some(429)
+        );
+
+        // 600 requests per minute refills one request every 100ms
+        let start = Instant::now();
+        limiter.acquire("text-embedding-ada-002", 1).await;
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[tokio::test]
+    async fn test_failed_request_releases_budget() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .respond_with(ResponseTemplate::new(500).set_body_json(json!({
+                "error": { "message": "server error", "type": "server_error" }
+            })))
+            .mount(&server)
+            .await;
+
+        let mut client = OpenAIClient::new("test-key", &server.uri());
+        let budgets = Arc::new(BudgetManager::new(
+            vec![Budget::hard(
+                BudgetScope::All,
+                BudgetLimit::Tokens(100),
+                None,
+            )],
+            Pricing::empty(),
+        ));
+        client.budgets = Some(budgets.clone());
+        let opts =
+            CreateEmbeddingsOptions::default("text-embedding-3-small", vec!["ribbit".repeat(40)]);
+        let prompt_tokens = opts.estimated_prompt_tokens();
+        assert!(prompt_tokens > 50 && prompt_tokens <= 100);
+
+        for _ in 0..2 {
+            client
+                .create_embeddings(&opts)
+                .await
+                .expect_err("the request should fail");
         }
+        budgets.release(
+            budgets
+                .check(None, "text-embedding-3-small", prompt_tokens, 0)
+                .expect("failed requests should release their reservations"),
+        );
     }
 }
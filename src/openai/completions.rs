@@ -1,3 +1,5 @@
+use crate::openai::metadata::ApiResponse;
+use crate::openai::tokens::estimate_tokens;
 use crate::openai::usage::Usage;
 use std::{collections::HashMap, error::Error};
 
@@ -48,6 +50,13 @@ impl CompletionOptions {
             user: None,
         }
     }
+
+    pub fn estimated_prompt_tokens(&self) -> u64 {
+        self.prompt
+            .iter()
+            .map(|prompt| estimate_tokens(prompt))
+            .sum()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -74,17 +83,30 @@ impl OpenAIClient {
         &self,
         opts: &CompletionOptions,
     ) -> Result<Completion, Box<dyn Error + Send + Sync>> {
-        let uri = self.base_uri.clone() + "/completions";
-        let api_key = &self.api_key;
-        let res = self
-            .client
-            .post(&uri)
-            .header("Authorization", format!("Bearer {api_key}"))
-            .json(&opts)
-            .send()
+        Ok(self.get_completion_with_metadata(opts).await?.data)
+    }
+
+    pub async fn get_completion_with_metadata(
+        &self,
+        opts: &CompletionOptions,
+    ) -> Result<ApiResponse<Completion>, Box<dyn Error + Send + Sync>> {
+        let choices = opts.best_of.unwrap_or(1).max(opts.n.unwrap_or(1)) as u64;
+        let max_tokens = opts.max_tokens.unwrap_or(16) as u64 * choices * opts.prompt.len() as u64;
+        let prompt_tokens = opts.estimated_prompt_tokens();
+        let reserved = self.check_budget(&opts.model, prompt_tokens, max_tokens)?;
+        self.wait_for_rate_limit(&opts.model, prompt_tokens + max_tokens)
+            .await;
+
+        let res: ApiResponse<Completion> = self
+            .post_json_rate_limited(&opts.model, "/completions", opts)
             .await?;
-        let completion: Completion = res.json().await?;
-        Ok(completion)
+        self.record_reserved_usage(
+            reserved,
+            &res.data.model,
+            opts.user.as_deref(),
+            &res.data.usage,
+        );
+        Ok(res)
     }
 }
 
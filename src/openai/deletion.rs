@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+/// Returned by every `DELETE` endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletionStatus {
+    pub id: String,
+    pub object: String,
+    pub deleted: bool,
+}
@@ -1,5 +1,6 @@
 use std::error::Error;
 
+use reqwest::Method;
 use serde::{Deserialize, Serialize};
 
 use crate::OpenAIClient;
@@ -20,13 +21,8 @@ impl OpenAIClient {
         &self,
         opts: &EditOptions,
     ) -> Result<reqwest::Response, Box<dyn Error + Send + Sync>> {
-        let uri = self.base_uri.clone() + "/embeddings";
-        let api_key = &self.api_key;
-
         let res = self
-            .client
-            .post(&uri)
-            .header("Authorization", format!("Bearer {api_key}"))
+            .request(Method::POST, "/embeddings")
             .json(&opts)
             .send()
             .await?;
@@ -34,7 +30,9 @@ impl OpenAIClient {
     }
 }
 
+// Scaffolding for tests that haven't been written yet
 #[cfg(test)]
+#[allow(unused_imports, dead_code)]
 mod tests {
     use super::*;
     use std::{env, sync::Once};
@@ -4,6 +4,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::OpenAIClient;
 
+use super::metadata::ApiResponse;
+use super::tokens::estimate_tokens;
 use super::usage::Usage;
 
 #[serde_with::skip_serializing_none]
@@ -22,6 +24,10 @@ impl CreateEmbeddingsOptions {
             user: None,
         }
     }
+
+    pub fn estimated_prompt_tokens(&self) -> u64 {
+        self.input.iter().map(|input| estimate_tokens(input)).sum()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -44,19 +50,27 @@ impl OpenAIClient {
         &self,
         opts: &CreateEmbeddingsOptions,
     ) -> Result<Embeddings, Box<dyn Error + Send + Sync>> {
-        let uri = self.base_uri.clone() + "/embeddings";
-        let api_key = &self.api_key;
-
-        let res = self
-            .client
-            .post(&uri)
-            .header("Authorization", format!("Bearer {api_key}"))
-            .json(&opts)
-            .send()
-            .await?;
+        Ok(self.create_embeddings_with_metadata(opts).await?.data)
+    }
 
-        let embeddings: Embeddings = res.json().await?;
-        Ok(embeddings)
+    pub async fn create_embeddings_with_metadata(
+        &self,
+        opts: &CreateEmbeddingsOptions,
+    ) -> Result<ApiResponse<Embeddings>, Box<dyn Error + Send + Sync>> {
+        let prompt_tokens = opts.estimated_prompt_tokens();
+        let reserved = self.check_budget(&opts.model, prompt_tokens, 0)?;
+        self.wait_for_rate_limit(&opts.model, prompt_tokens).await;
+
+        let res: ApiResponse<Embeddings> = self
+            .post_json_rate_limited(&opts.model, "/embeddings", opts)
+            .await?;
+        self.record_reserved_usage(
+            reserved,
+            &res.data.model,
+            opts.user.as_deref(),
+            &res.data.usage,
+        );
+        Ok(res)
     }
 }
 
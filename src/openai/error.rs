@@ -0,0 +1,60 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use serde::{Deserialize, Serialize};
+
+use super::metadata::ResponseMetadata;
+
+/// The `error` object OpenAI sends in the body of a failed request.
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiErrorBody {
+    pub message: String,
+    #[serde(rename = "type")]
+    pub error_type: Option<String>,
+    pub param: Option<String>,
+    pub code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiErrorEnvelope {
+    error: ApiErrorBody,
+}
+
+/// Returned (boxed) when OpenAI responds with a non-success status code.
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub error: ApiErrorBody,
+    pub metadata: ResponseMetadata,
+}
+
+impl ApiError {
+    pub(crate) fn new(metadata: ResponseMetadata, body: &[u8]) -> Self {
+        let error = match serde_json::from_slice::<ApiErrorEnvelope>(body) {
+            Ok(envelope) => envelope.error,
+            Err(_) => ApiErrorBody {
+                message: String::from_utf8_lossy(body).into_owned(),
+                error_type: None,
+                param: None,
+                code: None,
+            },
+        };
+        Self { error, metadata }
+    }
+
+    pub fn status(&self) -> u16 {
+        self.metadata.status
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OpenAI API error (status {}", self.metadata.status)?;
+        if let Some(code) = &self.error.code {
+            write!(f, ", {code}")?;
+        }
+        write!(f, "): {}", self.error.message)
+    }
+}
+
+impl Error for ApiError {}
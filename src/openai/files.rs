@@ -0,0 +1,328 @@
+use core::fmt;
+use std::error::Error;
+use std::fmt::Display;
+use std::path::Path;
+
+use bytes::Bytes;
+use futures_util::Stream;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+use crate::OpenAIClient;
+
+use super::deletion::DeletionStatus;
+use super::list::{ListOrder, ListResponse};
+use super::multipart::{FormBuilder, UploadSource};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FilePurpose {
+    #[serde(rename = "fine-tune")]
+    FineTune,
+    #[serde(rename = "fine-tune-results")]
+    FineTuneResults,
+    #[serde(rename = "batch")]
+    Batch,
+    #[serde(rename = "batch_output")]
+    BatchOutput,
+    #[serde(rename = "assistants")]
+    Assistants,
+    #[serde(rename = "assistants_output")]
+    AssistantsOutput,
+    #[serde(rename = "vision")]
+    Vision,
+    #[serde(rename = "user_data")]
+    UserData,
+}
+
+impl Display for FilePurpose {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilePurpose::FineTune => write!(f, "fine-tune"),
+            FilePurpose::FineTuneResults => write!(f, "fine-tune-results"),
+            FilePurpose::Batch => write!(f, "batch"),
+            FilePurpose::BatchOutput => write!(f, "batch_output"),
+            FilePurpose::Assistants => write!(f, "assistants"),
+            FilePurpose::AssistantsOutput => write!(f, "assistants_output"),
+            FilePurpose::Vision => write!(f, "vision"),
+            FilePurpose::UserData => write!(f, "user_data"),
+        }
+    }
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenAIFile {
+    pub id: String,
+    pub object: String,
+    pub bytes: u64,
+    pub created_at: u64,
+    pub filename: String,
+    pub purpose: FilePurpose,
+    pub status: Option<String>,
+    pub status_details: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct UploadFileOptions {
+    pub file_name: String,
+    pub purpose: FilePurpose,
+    pub content: UploadSource,
+}
+
+impl UploadFileOptions {
+    pub fn default(file_name: &str, purpose: FilePurpose, content: UploadSource) -> Self {
+        Self {
+            file_name: file_name.to_owned(),
+            purpose,
+            content,
+        }
+    }
+
+    /// Checks the file at `path` exists, to be streamed when it is uploaded.
+    pub async fn from_path(
+        path: impl AsRef<Path>,
+        purpose: FilePurpose,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let path = path.as_ref();
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or("upload path has no file name")?;
+        if !tokio::fs::metadata(path).await?.is_file() {
+            return Err("upload path is not a file".into());
+        }
+        Ok(Self::default(file_name, purpose, UploadSource::path(path)))
+    }
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ListFilesOptions {
+    pub purpose: Option<FilePurpose>,
+    pub limit: Option<u32>,
+    pub order: Option<ListOrder>,
+    pub after: Option<String>,
+}
+
+impl OpenAIClient {
+    pub async fn upload_file(
+        &self,
+        opts: UploadFileOptions,
+    ) -> Result<OpenAIFile, Box<dyn Error + Send + Sync>> {
+        let form = FormBuilder::new()
+            .text("purpose", &opts.purpose)
+            .file("file", &opts.file_name, None, opts.content)
+            .await?;
+
+        Ok(self.post_multipart("/files", form.build()).await?.data)
+    }
+
+    pub async fn list_files(
+        &self,
+        opts: &ListFilesOptions,
+    ) -> Result<ListResponse<OpenAIFile>, Box<dyn Error + Send + Sync>> {
+        let request = self.request(Method::GET, "/files").query(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    /// Follows `after` cursors until every file matching `purpose` has been listed.
+    pub async fn list_all_files(
+        &self,
+        purpose: Option<FilePurpose>,
+    ) -> Result<Vec<OpenAIFile>, Box<dyn Error + Send + Sync>> {
+        let mut opts = ListFilesOptions {
+            purpose,
+            ..Default::default()
+        };
+        let mut files = vec![];
+        loop {
+            let page = self.list_files(&opts).await?;
+            let last_id = page.data.last().map(|file| file.id.clone());
+            files.extend(page.data);
+            match last_id {
+                Some(last_id) if page.has_more => opts.after = Some(last_id),
+                _ => return Ok(files),
+            }
+        }
+    }
+
+    pub async fn get_file(
+        &self,
+        file_id: &str,
+    ) -> Result<OpenAIFile, Box<dyn Error + Send + Sync>> {
+        Ok(self.get_json(&format!("/files/{file_id}")).await?.data)
+    }
+
+    pub async fn download_file(
+        &self,
+        file_id: &str,
+    ) -> Result<Bytes, Box<dyn Error + Send + Sync>> {
+        let request = self.request(Method::GET, &format!("/files/{file_id}/content"));
+        Ok(self.send(request).await?.bytes().await?)
+    }
+
+    /// Like [`OpenAIClient::download_file`], but yields the contents in chunks as they arrive.
+    pub async fn download_file_stream(
+        &self,
+        file_id: &str,
+    ) -> Result<impl Stream<Item = reqwest::Result<Bytes>>, Box<dyn Error + Send + Sync>> {
+        let request = self.request(Method::GET, &format!("/files/{file_id}/content"));
+        Ok(self.send(request).await?.bytes_stream())
+    }
+
+    pub async fn delete_file(
+        &self,
+        file_id: &str,
+    ) -> Result<DeletionStatus, Box<dyn Error + Send + Sync>> {
+        Ok(self.delete_json(&format!("/files/{file_id}")).await?.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures_util::StreamExt;
+    use serde_json::json;
+    use std::{env, sync::Once};
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    static INIT: Once = Once::new();
+
+    fn initialize() {
+        INIT.call_once(|| {
+            dotenvy::dotenv().expect("error loading environment variables");
+        });
+    }
+
+    fn file_json(id: &str) -> serde_json::Value {
+        json!({
+            "id": id,
+            "object": "file",
+            "bytes": 120000,
+            "created_at": 1677610602,
+            "filename": "train.jsonl",
+            "purpose": "fine-tune"
+        })
+    }
+
+    #[tokio::test]
+    pub async fn test_list_files() {
+        initialize();
+        let api_key = env::var("OPENAI_API_KEY").expect("error loading API key");
+        let client = OpenAIClient::new(&api_key, "https://api.openai.com/v1");
+        let _files = client
+            .list_files(&ListFilesOptions::default())
+            .await
+            .expect("error listing files");
+    }
+
+    #[tokio::test]
+    pub async fn test_upload_file_from_path() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/files"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(file_json("file-1")))
+            .mount(&server)
+            .await;
+
+        let toad_img_path = env::current_dir()
+            .expect("error getting current directory")
+            .join("assets/toad.png");
+        let opts = UploadFileOptions::from_path(&toad_img_path, FilePurpose::Vision)
+            .await
+            .expect("error opening file");
+        assert_eq!(opts.file_name, "toad.png");
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let file = client
+            .upload_file(opts)
+            .await
+            .expect("error uploading file");
+        assert_eq!(file.id, "file-1");
+
+        let requests = server.received_requests().await.expect("no requests");
+        let body = &requests[0].body;
+        let toad = std::fs::read(&toad_img_path).expect("error loading image");
+        assert!(body.windows(toad.len()).any(|window| window == toad));
+        let body = String::from_utf8_lossy(body);
+        assert!(body.contains("name=\"purpose\"\r\n\r\nvision\r\n"));
+        assert!(body.contains("name=\"file\"; filename=\"toad.png\""));
+    }
+
+    #[tokio::test]
+    pub async fn test_list_all_files() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/files"))
+            .and(query_param("after", "file-2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "object": "list",
+                "data": [file_json("file-3")],
+                "has_more": false
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/files"))
+            .and(query_param("purpose", "fine-tune"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "object": "list",
+                "data": [file_json("file-1"), file_json("file-2")],
+                "has_more": true
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let files = client
+            .list_all_files(Some(FilePurpose::FineTune))
+            .await
+            .expect("error listing files");
+        let ids: Vec<&str> = files.iter().map(|file| file.id.as_str()).collect();
+        assert_eq!(ids, ["file-1", "file-2", "file-3"]);
+    }
+
+    #[tokio::test]
+    pub async fn test_download_and_delete_file() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/files/file-1/content"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("{\"a\":1}\n{\"a\":2}\n"))
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/files/file-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "file-1",
+                "object": "file",
+                "deleted": true
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let content = client
+            .download_file("file-1")
+            .await
+            .expect("error downloading file");
+        assert_eq!(&content[..], b"{\"a\":1}\n{\"a\":2}\n");
+
+        let mut stream = client
+            .download_file_stream("file-1")
+            .await
+            .expect("error downloading file");
+        let mut streamed = vec![];
+        while let Some(chunk) = stream.next().await {
+            streamed.extend_from_slice(&chunk.expect("error reading chunk"));
+        }
+        assert_eq!(streamed, content);
+
+        let deleted = client
+            .delete_file("file-1")
+            .await
+            .expect("error deleting file");
+        assert!(deleted.deleted);
+    }
+}
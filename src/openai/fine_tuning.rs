@@ -0,0 +1,419 @@
+use std::error::Error;
+
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::OpenAIClient;
+
+use super::list::{ListOptions, ListResponse};
+use super::poll::{Backoff, PollOptions};
+
+/// Each hyperparameter is either a number or `"auto"`, so they are kept as JSON values.
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Hyperparameters {
+    pub n_epochs: Option<Value>,
+    pub batch_size: Option<Value>,
+    pub learning_rate_multiplier: Option<Value>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateFineTuningJobOptions {
+    pub model: String,
+    pub training_file: String,
+    pub validation_file: Option<String>,
+    pub hyperparameters: Option<Hyperparameters>,
+    pub suffix: Option<String>,
+    pub seed: Option<u64>,
+}
+
+impl CreateFineTuningJobOptions {
+    pub fn default(model: &str, training_file: &str) -> Self {
+        Self {
+            model: model.to_owned(),
+            training_file: training_file.to_owned(),
+            validation_file: None,
+            hyperparameters: None,
+            suffix: None,
+            seed: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FineTuningJobStatus {
+    #[serde(rename = "validating_files")]
+    ValidatingFiles,
+    #[serde(rename = "queued")]
+    Queued,
+    #[serde(rename = "running")]
+    Running,
+    #[serde(rename = "succeeded")]
+    Succeeded,
+    #[serde(rename = "failed")]
+    Failed,
+    #[serde(rename = "cancelled")]
+    Cancelled,
+}
+
+impl FineTuningJobStatus {
+    /// Whether the job has stopped and will not change status again.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            FineTuningJobStatus::Succeeded
+                | FineTuningJobStatus::Failed
+                | FineTuningJobStatus::Cancelled
+        )
+    }
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FineTuningJob {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub finished_at: Option<u64>,
+    pub estimated_finish: Option<u64>,
+    pub model: String,
+    pub fine_tuned_model: Option<String>,
+    pub organization_id: String,
+    pub status: FineTuningJobStatus,
+    pub hyperparameters: Hyperparameters,
+    pub training_file: String,
+    pub validation_file: Option<String>,
+    #[serde(default)]
+    pub result_files: Vec<String>,
+    pub trained_tokens: Option<u64>,
+    pub error: Option<Value>,
+    pub seed: Option<u64>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FineTuningJobEvent {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub level: String,
+    pub message: String,
+    #[serde(rename = "type")]
+    pub event_type: Option<String>,
+    pub data: Option<Value>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FineTuningCheckpoint {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub fine_tuned_model_checkpoint: String,
+    pub fine_tuning_job_id: String,
+    pub step_number: u64,
+    pub metrics: Value,
+}
+
+impl OpenAIClient {
+    pub async fn create_fine_tuning_job(
+        &self,
+        opts: &CreateFineTuningJobOptions,
+    ) -> Result<FineTuningJob, Box<dyn Error + Send + Sync>> {
+        Ok(self.post_json("/fine_tuning/jobs", opts).await?.data)
+    }
+
+    pub async fn list_fine_tuning_jobs(
+        &self,
+        opts: &ListOptions,
+    ) -> Result<ListResponse<FineTuningJob>, Box<dyn Error + Send + Sync>> {
+        let request = self.request(Method::GET, "/fine_tuning/jobs").query(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn get_fine_tuning_job(
+        &self,
+        job_id: &str,
+    ) -> Result<FineTuningJob, Box<dyn Error + Send + Sync>> {
+        Ok(self
+            .get_json(&format!("/fine_tuning/jobs/{job_id}"))
+            .await?
+            .data)
+    }
+
+    pub async fn cancel_fine_tuning_job(
+        &self,
+        job_id: &str,
+    ) -> Result<FineTuningJob, Box<dyn Error + Send + Sync>> {
+        let request = self.request(Method::POST, &format!("/fine_tuning/jobs/{job_id}/cancel"));
+        Ok(self.send_json(request).await?.data)
+    }
+
+    /// Events are returned newest first.
+    pub async fn list_fine_tuning_events(
+        &self,
+        job_id: &str,
+        opts: &ListOptions,
+    ) -> Result<ListResponse<FineTuningJobEvent>, Box<dyn Error + Send + Sync>> {
+        let request = self
+            .request(Method::GET, &format!("/fine_tuning/jobs/{job_id}/events"))
+            .query(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn list_fine_tuning_checkpoints(
+        &self,
+        job_id: &str,
+        opts: &ListOptions,
+    ) -> Result<ListResponse<FineTuningCheckpoint>, Box<dyn Error + Send + Sync>> {
+        let request = self
+            .request(
+                Method::GET,
+                &format!("/fine_tuning/jobs/{job_id}/checkpoints"),
+            )
+            .query(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    /// Lists the events newer than `last_event_id`, newest first, following `after` cursors so
+    /// none are missed however many arrived since.
+    async fn new_fine_tuning_events(
+        &self,
+        job_id: &str,
+        last_event_id: Option<&str>,
+    ) -> Result<Vec<FineTuningJobEvent>, Box<dyn Error + Send + Sync>> {
+        let mut opts = ListOptions {
+            limit: Some(100),
+            ..Default::default()
+        };
+        let mut new_events = vec![];
+        loop {
+            let page = self.list_fine_tuning_events(job_id, &opts).await?;
+            let has_more = page.has_more;
+            for event in page.data {
+                if Some(event.id.as_str()) == last_event_id {
+                    return Ok(new_events);
+                }
+                new_events.push(event);
+            }
+            match new_events.last() {
+                Some(oldest) if has_more => opts.after = Some(oldest.id.clone()),
+                _ => return Ok(new_events),
+            }
+        }
+    }
+
+    /// Polls a job until it succeeds, fails or is cancelled, calling `on_event` with each new job
+    /// event in the order they happened. Returns the finished job; check its `status` to see how
+    /// it ended.
+    pub async fn wait_for_fine_tuning_job(
+        &self,
+        job_id: &str,
+        poll: &PollOptions,
+        mut on_event: impl FnMut(&FineTuningJobEvent),
+    ) -> Result<FineTuningJob, Box<dyn Error + Send + Sync>> {
+        let mut backoff = Backoff::new(poll);
+        let mut last_event_id: Option<String> = None;
+        loop {
+            let job = self.get_fine_tuning_job(job_id).await?;
+
+            let new_events = self
+                .new_fine_tuning_events(job_id, last_event_id.as_deref())
+                .await?;
+            for event in new_events.iter().rev() {
+                on_event(event);
+            }
+            if let Some(newest) = new_events.first() {
+                last_event_id = Some(newest.id.clone());
+            }
+
+            if job.status.is_finished() {
+                return Ok(job);
+            }
+            backoff.wait().await?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+    use std::time::Duration;
+    use std::{env, sync::Once};
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    static INIT: Once = Once::new();
+
+    fn initialize() {
+        INIT.call_once(|| {
+            dotenvy::dotenv().expect("error loading environment variables");
+        });
+    }
+
+    fn job_json(status: &str) -> Value {
+        json!({
+            "id": "ftjob-1",
+            "object": "fine_tuning.job",
+            "created_at": 1692661014,
+            "finished_at": null,
+            "model": "gpt-3.5-turbo-0613",
+            "fine_tuned_model": null,
+            "organization_id": "org-1",
+            "status": status,
+            "hyperparameters": { "n_epochs": "auto" },
+            "training_file": "file-1",
+            "validation_file": null,
+            "result_files": [],
+            "trained_tokens": null
+        })
+    }
+
+    fn event_json(id: &str, message: &str) -> Value {
+        json!({
+            "id": id,
+            "object": "fine_tuning.job.event",
+            "created_at": 1692661014,
+            "level": "info",
+            "message": message,
+            "type": "message"
+        })
+    }
+
+    #[tokio::test]
+    pub async fn test_list_fine_tuning_jobs() {
+        initialize();
+        let api_key = env::var("OPENAI_API_KEY").expect("error loading API key");
+        let client = OpenAIClient::new(&api_key, "https://api.openai.com/v1");
+        let _jobs = client
+            .list_fine_tuning_jobs(&ListOptions::default())
+            .await
+            .expect("error listing fine-tuning jobs");
+    }
+
+    #[tokio::test]
+    pub async fn test_wait_for_fine_tuning_job() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/fine_tuning/jobs/ftjob-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(job_json("running")))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/fine_tuning/jobs/ftjob-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(job_json("succeeded")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/fine_tuning/jobs/ftjob-1/events"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "object": "list",
+                "data": [event_json("ev-2", "Step 1/10"), event_json("ev-1", "Job started")],
+                "has_more": false
+            })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/fine_tuning/jobs/ftjob-1/events"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "object": "list",
+                "data": [
+                    event_json("ev-3", "Job succeeded"),
+                    event_json("ev-2", "Step 1/10"),
+                    event_json("ev-1", "Job started")
+                ],
+                "has_more": false
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let poll = PollOptions {
+            initial_interval: Duration::from_millis(5),
+            ..Default::default()
+        };
+        let mut messages = vec![];
+        let job = client
+            .wait_for_fine_tuning_job("ftjob-1", &poll, |event| {
+                messages.push(event.message.clone())
+            })
+            .await
+            .expect("error waiting for fine-tuning job");
+
+        assert_eq!(job.status, FineTuningJobStatus::Succeeded);
+        assert_eq!(messages, ["Job started", "Step 1/10", "Job succeeded"]);
+    }
+
+    #[tokio::test]
+    pub async fn test_wait_for_fine_tuning_job_pages_events() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/fine_tuning/jobs/ftjob-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(job_json("running")))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/fine_tuning/jobs/ftjob-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(job_json("succeeded")))
+            .mount(&server)
+            .await;
+        // The first poll sees one event, then more than a page arrives before the second
+        Mock::given(method("GET"))
+            .and(path("/fine_tuning/jobs/ftjob-1/events"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "object": "list",
+                "data": [event_json("ev-1", "Job started")],
+                "has_more": false
+            })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/fine_tuning/jobs/ftjob-1/events"))
+            .and(query_param("after", "ev-3"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "object": "list",
+                "data": [event_json("ev-2", "Step 1/10"), event_json("ev-1", "Job started")],
+                "has_more": false
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/fine_tuning/jobs/ftjob-1/events"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "object": "list",
+                "data": [
+                    event_json("ev-4", "Job succeeded"),
+                    event_json("ev-3", "Step 2/10")
+                ],
+                "has_more": true
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let poll = PollOptions {
+            initial_interval: Duration::from_millis(5),
+            ..Default::default()
+        };
+        let mut messages = vec![];
+        client
+            .wait_for_fine_tuning_job("ftjob-1", &poll, |event| {
+                messages.push(event.message.clone())
+            })
+            .await
+            .expect("error waiting for fine-tuning job");
+
+        assert_eq!(
+            messages,
+            ["Job started", "Step 1/10", "Step 2/10", "Job succeeded"]
+        );
+    }
+}
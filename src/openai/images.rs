@@ -1,18 +1,32 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
 use core::fmt;
-use reqwest::multipart;
-use serde::{Deserialize, Serialize};
-use std::{error::Error, fmt::Display};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    error::Error,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
 
 use crate::OpenAIClient;
 
-#[derive(Debug, Serialize, Deserialize)]
+use super::{
+    metadata::ApiResponse,
+    multipart::{FormBuilder, UploadSource},
+    png::read_png_info,
+};
+
+/// `Custom` is sent as-is, for sizes newer than this enum.
+#[derive(Debug, Clone, PartialEq)]
 pub enum ImgSize {
-    #[serde(rename = "256x256")]
     Size256x256,
-    #[serde(rename = "512x512")]
     Size512x512,
-    #[serde(rename = "1024x1024")]
     Size1024x1024,
+    Size1792x1024,
+    Size1024x1792,
+    Size1536x1024,
+    Size1024x1536,
+    Auto,
+    Custom(String),
 }
 
 impl Display for ImgSize {
@@ -21,11 +35,241 @@ impl Display for ImgSize {
             ImgSize::Size256x256 => write!(f, "256x256"),
             ImgSize::Size512x512 => write!(f, "512x512"),
             ImgSize::Size1024x1024 => write!(f, "1024x1024"),
+            ImgSize::Size1792x1024 => write!(f, "1792x1024"),
+            ImgSize::Size1024x1792 => write!(f, "1024x1792"),
+            ImgSize::Size1536x1024 => write!(f, "1536x1024"),
+            ImgSize::Size1024x1536 => write!(f, "1024x1536"),
+            ImgSize::Auto => write!(f, "auto"),
+            ImgSize::Custom(size) => write!(f, "{size}"),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl From<&str> for ImgSize {
+    fn from(size: &str) -> Self {
+        match size {
+            "256x256" => ImgSize::Size256x256,
+            "512x512" => ImgSize::Size512x512,
+            "1024x1024" => ImgSize::Size1024x1024,
+            "1792x1024" => ImgSize::Size1792x1024,
+            "1024x1792" => ImgSize::Size1024x1792,
+            "1536x1024" => ImgSize::Size1536x1024,
+            "1024x1536" => ImgSize::Size1024x1536,
+            "auto" => ImgSize::Auto,
+            size => ImgSize::Custom(size.to_owned()),
+        }
+    }
+}
+
+impl ImgSize {
+    /// Width and height in pixels. `None` for `Auto` and custom sizes that aren't `{w}x{h}`.
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        let size = self.to_string();
+        let (width, height) = size.split_once('x')?;
+        Some((width.parse().ok()?, height.parse().ok()?))
+    }
+}
+
+impl Serialize for ImgSize {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ImgSize {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(ImgSize::from(String::deserialize(deserializer)?.as_str()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ImgQuality {
+    /// dall-e-3
+    #[serde(rename = "standard")]
+    Standard,
+    /// dall-e-3
+    #[serde(rename = "hd")]
+    Hd,
+    /// gpt-image models
+    #[serde(rename = "low")]
+    Low,
+    /// gpt-image models
+    #[serde(rename = "medium")]
+    Medium,
+    /// gpt-image models
+    #[serde(rename = "high")]
+    High,
+    #[serde(rename = "auto")]
+    Auto,
+}
+
+impl Display for ImgQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImgQuality::Standard => write!(f, "standard"),
+            ImgQuality::Hd => write!(f, "hd"),
+            ImgQuality::Low => write!(f, "low"),
+            ImgQuality::Medium => write!(f, "medium"),
+            ImgQuality::High => write!(f, "high"),
+            ImgQuality::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+/// dall-e-3 only
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ImgStyle {
+    #[serde(rename = "vivid")]
+    Vivid,
+    #[serde(rename = "natural")]
+    Natural,
+}
+
+impl Display for ImgStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImgStyle::Vivid => write!(f, "vivid"),
+            ImgStyle::Natural => write!(f, "natural"),
+        }
+    }
+}
+
+/// gpt-image models only
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ImgBackground {
+    #[serde(rename = "transparent")]
+    Transparent,
+    #[serde(rename = "opaque")]
+    Opaque,
+    #[serde(rename = "auto")]
+    Auto,
+}
+
+impl Display for ImgBackground {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImgBackground::Transparent => write!(f, "transparent"),
+            ImgBackground::Opaque => write!(f, "opaque"),
+            ImgBackground::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+/// gpt-image models only
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ImgOutputFormat {
+    #[serde(rename = "png")]
+    Png,
+    #[serde(rename = "jpeg")]
+    Jpeg,
+    #[serde(rename = "webp")]
+    Webp,
+}
+
+impl Display for ImgOutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImgOutputFormat::Png => write!(f, "png"),
+            ImgOutputFormat::Jpeg => write!(f, "jpeg"),
+            ImgOutputFormat::Webp => write!(f, "webp"),
+        }
+    }
+}
+
+/// Returned (boxed) instead of sending an image request the model would reject.
+#[derive(Debug, Clone)]
+pub struct InvalidImgOptions {
+    pub model: String,
+    pub reason: String,
+}
+
+impl Display for InvalidImgOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid options for {}: {}", self.model, self.reason)
+    }
+}
+
+impl Error for InvalidImgOptions {}
+
+/// The largest image or mask, in bytes, the edits and variations endpoints accept. The limit is
+/// inclusive.
+pub const MAX_IMG_UPLOAD_SIZE: usize = 4 * 1024 * 1024;
+
+/// Returned (boxed) when an image or mask would be rejected by the edits or variations endpoint.
+/// `part` is the multipart field at fault, `image` or `mask`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvalidImg {
+    NotPng {
+        part: &'static str,
+    },
+    TooLarge {
+        part: &'static str,
+        size: usize,
+    },
+    NotSquare {
+        width: u32,
+        height: u32,
+    },
+    /// Both as (width, height)
+    MaskSizeMismatch {
+        img: (u32, u32),
+        mask: (u32, u32),
+    },
+    /// The mask has no alpha channel, or there is no mask and the image has none
+    NoTransparency {
+        part: &'static str,
+    },
+}
+
+impl Display for InvalidImg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidImg::NotPng { part } => write!(f, "{part} is not a PNG"),
+            InvalidImg::TooLarge { part, size } => write!(
+                f,
+                "{part} is {size} bytes, over the {MAX_IMG_UPLOAD_SIZE} byte limit"
+            ),
+            InvalidImg::NotSquare { width, height } => {
+                write!(f, "image is {width}x{height}, not square")
+            }
+            InvalidImg::MaskSizeMismatch { img, mask } => write!(
+                f,
+                "mask is {}x{} but image is {}x{}",
+                mask.0, mask.1, img.0, img.1
+            ),
+            InvalidImg::NoTransparency { part } => {
+                write!(f, "{part} has no transparent areas to edit")
+            }
+        }
+    }
+}
+
+impl Error for InvalidImg {}
+
+/// Checks `img` is within the upload limit and, unless it is a reader, that it is a square PNG.
+/// Returns its width, height and whether it has an alpha channel, if its header could be read.
+async fn validate_img(
+    part: &'static str,
+    img: &UploadSource,
+) -> Result<Option<(u32, u32, bool)>, Box<dyn Error + Send + Sync>> {
+    let size = usize::try_from(img.size().await?).unwrap_or(usize::MAX);
+    if size > MAX_IMG_UPLOAD_SIZE {
+        return Err(Box::new(InvalidImg::TooLarge { part, size }));
+    }
+    let Some(head) = img.head().await? else {
+        return Ok(None);
+    };
+    let info = read_png_info(&head).ok_or(InvalidImg::NotPng { part })?;
+    if info.width != info.height {
+        return Err(Box::new(InvalidImg::NotSquare {
+            width: info.width,
+            height: info.height,
+        }));
+    }
+    Ok(Some((info.width, info.height, info.has_alpha)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum ImgFormat {
     #[serde(rename = "url")]
     Url,
@@ -35,7 +279,7 @@ pub enum ImgFormat {
 
 /// OpenAI only supports sending images as PNGs. This enum will be updated if/when they update
 /// their API
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum ImgType {
     Png,
 }
@@ -61,19 +305,25 @@ impl Display for ImgType {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateImgOptions {
     pub prompt: String,
+    /// `dall-e-2` if not set
+    pub model: Option<String>,
     pub n: Option<u8>,
     pub size: Option<ImgSize>,
+    pub quality: Option<ImgQuality>,
+    pub style: Option<ImgStyle>,
+    pub background: Option<ImgBackground>,
+    pub output_format: Option<ImgOutputFormat>,
+    /// gpt-image models always return base64 and reject this field, so set it to `None` for them
     pub response_format: Option<ImgFormat>,
     pub user: Option<String>,
 }
 
-#[serde_with::skip_serializing_none]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug)]
 pub struct EditImgOptions {
     pub file_name: String,
-    pub img: Vec<u8>,
+    pub img: UploadSource,
     pub prompt: String,
-    pub mask: Option<Vec<u8>>,
+    pub mask: Option<UploadSource>,
     pub n: Option<u8>,
     pub size: Option<ImgSize>,
     pub response_format: Option<ImgFormat>,
@@ -81,11 +331,10 @@ pub struct EditImgOptions {
     pub img_type: ImgType,
 }
 
-#[serde_with::skip_serializing_none]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug)]
 pub struct CreateImgVariationsOptions {
     pub file_name: String,
-    pub img: Vec<u8>,
+    pub img: UploadSource,
     pub n: Option<u8>,
     pub size: Option<ImgSize>,
     pub response_format: Option<ImgFormat>,
@@ -97,16 +346,115 @@ impl CreateImgOptions {
     pub fn default(prompt: &str) -> Self {
         Self {
             prompt: prompt.to_owned(),
+            model: None,
             n: Some(1),
             size: Some(ImgSize::Size256x256),
+            quality: None,
+            style: None,
+            background: None,
+            output_format: None,
             response_format: Some(ImgFormat::Url),
             user: None,
         }
     }
+
+    /// Checks the options against the documented constraints of the model. Unknown models and
+    /// custom sizes are not checked.
+    pub fn validate(&self) -> Result<(), InvalidImgOptions> {
+        let model = self.model.as_deref().unwrap_or("dall-e-2");
+        let invalid = |reason: &str| {
+            Err(InvalidImgOptions {
+                model: model.to_owned(),
+                reason: reason.to_owned(),
+            })
+        };
+
+        let (max_n, max_prompt, sizes, qualities): (u8, usize, &[ImgSize], &[ImgQuality]) =
+            if model.starts_with("dall-e-2") {
+                (
+                    10,
+                    1000,
+                    &[
+                        ImgSize::Size256x256,
+                        ImgSize::Size512x512,
+                        ImgSize::Size1024x1024,
+                    ],
+                    &[ImgQuality::Standard],
+                )
+            } else if model.starts_with("dall-e-3") {
+                (
+                    1,
+                    4000,
+                    &[
+                        ImgSize::Size1024x1024,
+                        ImgSize::Size1792x1024,
+                        ImgSize::Size1024x1792,
+                    ],
+                    &[ImgQuality::Standard, ImgQuality::Hd],
+                )
+            } else if model.starts_with("gpt-image") {
+                (
+                    10,
+                    32000,
+                    &[
+                        ImgSize::Size1024x1024,
+                        ImgSize::Size1536x1024,
+                        ImgSize::Size1024x1536,
+                        ImgSize::Auto,
+                    ],
+                    &[
+                        ImgQuality::Low,
+                        ImgQuality::Medium,
+                        ImgQuality::High,
+                        ImgQuality::Auto,
+                    ],
+                )
+            } else {
+                return Ok(());
+            };
+
+        if let Some(n) = self.n {
+            if n < 1 || n > max_n {
+                return invalid(&format!("n must be between 1 and {max_n}"));
+            }
+        }
+        if self.prompt.chars().count() > max_prompt {
+            return invalid(&format!("prompt is longer than {max_prompt} characters"));
+        }
+        match &self.size {
+            Some(ImgSize::Custom(_)) | None => {}
+            Some(size) if !sizes.contains(size) => {
+                return invalid(&format!("size {size} is not supported"));
+            }
+            _ => {}
+        }
+        if let Some(quality) = self.quality {
+            if !qualities.contains(&quality) {
+                return invalid(&format!("quality {quality} is not supported"));
+            }
+        }
+
+        let gpt_image = model.starts_with("gpt-image");
+        if self.style.is_some() && !model.starts_with("dall-e-3") {
+            return invalid("style is only supported by dall-e-3");
+        }
+        if !gpt_image && (self.background.is_some() || self.output_format.is_some()) {
+            return invalid("background and output_format are only supported by gpt-image models");
+        }
+        if gpt_image && self.response_format.is_some() {
+            return invalid("gpt-image models always return base64; response_format must be None");
+        }
+        if self.background == Some(ImgBackground::Transparent)
+            && self.output_format == Some(ImgOutputFormat::Jpeg)
+        {
+            return invalid("a transparent background needs png or webp output");
+        }
+        Ok(())
+    }
 }
 
 impl EditImgOptions {
-    pub fn default(file_name: &str, img: Vec<u8>, img_type: ImgType, prompt: &str) -> Self {
+    pub fn default(file_name: &str, img: UploadSource, img_type: ImgType, prompt: &str) -> Self {
         Self {
             file_name: file_name.to_owned(),
             img,
@@ -119,10 +467,38 @@ impl EditImgOptions {
             img_type,
         }
     }
+
+    /// Checks the image and mask are square PNGs under 4 MB with the same dimensions, and that
+    /// the mask, or the image if there is no mask, has transparent areas to edit. Files are only
+    /// read as far as their headers, and readers only have their length checked. Errors are
+    /// [`InvalidImg`] unless a file couldn't be read.
+    pub async fn validate(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let img = validate_img("image", &self.img).await?;
+        let invalid = match (&self.mask, img) {
+            (Some(mask), img) => match (validate_img("mask", mask).await?, img) {
+                (Some((width, height, _)), Some((img_width, img_height, _)))
+                    if (width, height) != (img_width, img_height) =>
+                {
+                    Some(InvalidImg::MaskSizeMismatch {
+                        img: (img_width, img_height),
+                        mask: (width, height),
+                    })
+                }
+                (Some((_, _, false)), _) => Some(InvalidImg::NoTransparency { part: "mask" }),
+                _ => None,
+            },
+            (None, Some((_, _, false))) => Some(InvalidImg::NoTransparency { part: "image" }),
+            (None, _) => None,
+        };
+        match invalid {
+            Some(invalid) => Err(Box::new(invalid)),
+            None => Ok(()),
+        }
+    }
 }
 
 impl CreateImgVariationsOptions {
-    pub fn default(file_name: &str, img: Vec<u8>, img_type: ImgType) -> Self {
+    pub fn default(file_name: &str, img: UploadSource, img_type: ImgType) -> Self {
         Self {
             file_name: file_name.to_owned(),
             img,
@@ -133,18 +509,111 @@ impl CreateImgVariationsOptions {
             img_type,
         }
     }
+
+    /// Checks the image is a square PNG under 4 MB, as far as [`EditImgOptions::validate`] can.
+    pub async fn validate(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        validate_img("image", &self.img).await?;
+        Ok(())
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Img {
-    #[serde(alias = "url", alias = "b64_json")]
-    img_data: String,
+/// One generated image, in the shape requested by `response_format`. `revised_prompt` is the
+/// prompt dall-e-3 actually used, after rewriting the one it was given.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Img {
+    Url {
+        url: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        revised_prompt: Option<String>,
+    },
+    B64Json {
+        b64_json: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        revised_prompt: Option<String>,
+    },
 }
 
+impl Img {
+    pub fn url(&self) -> Option<&str> {
+        match self {
+            Img::Url { url, .. } => Some(url),
+            Img::B64Json { .. } => None,
+        }
+    }
+
+    pub fn revised_prompt(&self) -> Option<&str> {
+        match self {
+            Img::Url { revised_prompt, .. } | Img::B64Json { revised_prompt, .. } => {
+                revised_prompt.as_deref()
+            }
+        }
+    }
+
+    /// Decodes a base64 image. Returns `None` for URL images, which have to be downloaded.
+    pub fn decode(&self) -> Result<Option<Vec<u8>>, Box<dyn Error + Send + Sync>> {
+        match self {
+            Img::Url { .. } => Ok(None),
+            Img::B64Json { b64_json, .. } => Ok(Some(STANDARD.decode(b64_json)?)),
+        }
+    }
+}
+
+#[serde_with::skip_serializing_none]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ImgResponse {
-    created: u64,
-    data: Vec<Img>,
+    pub created: u64,
+    pub data: Vec<Img>,
+    /// Only returned by gpt-image models
+    pub output_format: Option<ImgOutputFormat>,
+}
+
+impl ImgResponse {
+    /// Returns the bytes of every image, in order. Base64 images are decoded and URL images are
+    /// downloaded with the client's `reqwest::Client`, without the API key.
+    pub async fn to_bytes(
+        &self,
+        client: &OpenAIClient,
+    ) -> Result<Vec<Vec<u8>>, Box<dyn Error + Send + Sync>> {
+        let mut images = Vec::with_capacity(self.data.len());
+        for img in &self.data {
+            let bytes = match img {
+                Img::B64Json { b64_json, .. } => STANDARD.decode(b64_json)?,
+                Img::Url { url, .. } => client
+                    .client
+                    .get(url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .bytes()
+                    .await?
+                    .to_vec(),
+            };
+            images.push(bytes);
+        }
+        Ok(images)
+    }
+
+    /// Writes every image to `dir` (created if missing) as `img-{created}-{index}.{ext}` and
+    /// returns the paths in order. The extension comes from `output_format`, or `png` if the model
+    /// didn't return one.
+    pub async fn save(
+        &self,
+        client: &OpenAIClient,
+        dir: impl AsRef<Path>,
+    ) -> Result<Vec<PathBuf>, Box<dyn Error + Send + Sync>> {
+        let dir = dir.as_ref();
+        tokio::fs::create_dir_all(dir).await?;
+        let ext = self.output_format.unwrap_or(ImgOutputFormat::Png);
+
+        let mut paths = Vec::with_capacity(self.data.len());
+        for (index, bytes) in self.to_bytes(client).await?.into_iter().enumerate() {
+            let path = dir.join(format!("img-{}-{index}.{ext}", self.created));
+            tokio::fs::write(&path, bytes).await?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
 }
 
 impl OpenAIClient {
@@ -152,121 +621,146 @@ impl OpenAIClient {
         &self,
         opts: &CreateImgOptions,
     ) -> Result<ImgResponse, Box<dyn Error + Send + Sync>> {
-        let uri = self.base_uri.clone() + "/images/generations";
-        let api_key = &self.api_key;
-        let res = self
-            .client
-            .post(&uri)
-            .header("Authorization", format!("Bearer {api_key}"))
-            .json(&opts)
-            .send()
-            .await?;
-        let images: ImgResponse = res.json().await?;
-        Ok(images)
+        Ok(self.create_img_with_metadata(opts).await?.data)
+    }
+
+    pub async fn create_img_with_metadata(
+        &self,
+        opts: &CreateImgOptions,
+    ) -> Result<ApiResponse<ImgResponse>, Box<dyn Error + Send + Sync>> {
+        opts.validate()?;
+        self.post_json("/images/generations", opts).await
     }
 
+    /// Sends `opts` without consuming it, so its image and mask must be bytes or paths. Use
+    /// [`OpenAIClient::edit_img_owned`] for readers.
     pub async fn edit_img(
         &self,
         opts: &EditImgOptions,
     ) -> Result<ImgResponse, Box<dyn Error + Send + Sync>> {
-        let uri = self.base_uri.clone() + "/images/edits";
-        let api_key = &self.api_key;
-
-        let mut form_data = multipart::Form::new();
-
-        let img = multipart::Part::bytes(opts.img.clone())
-            .file_name(opts.file_name.to_owned())
-            .mime_str(&opts.img_type.to_string())?;
-
-        let prompt = multipart::Part::bytes(opts.prompt.as_bytes().to_owned());
-
-        form_data = form_data
-            .part("image".to_owned(), img)
-            .part("prompt".to_owned(), prompt);
+        Ok(self.edit_img_with_metadata(opts).await?.data)
+    }
 
-        if let Some(n) = opts.n {
-            // let n_bytes = multipart::Part::bytes(n.to_le_bytes().to_vec());
-            // form_data = form_data.part("n".to_owned(), n_bytes)
-            form_data = form_data.text::<String, String>("n".to_owned(), n.to_string());
+    pub async fn edit_img_with_metadata(
+        &self,
+        opts: &EditImgOptions,
+    ) -> Result<ApiResponse<ImgResponse>, Box<dyn Error + Send + Sync>> {
+        let mask = match &opts.mask {
+            Some(mask) => Some(reusable("mask", mask)?),
+            None => None,
         };
+        let opts = EditImgOptions {
+            file_name: opts.file_name.clone(),
+            img: reusable("image", &opts.img)?,
+            prompt: opts.prompt.clone(),
+            mask,
+            n: opts.n,
+            size: opts.size.clone(),
+            response_format: opts.response_format,
+            user: opts.user.clone(),
+            img_type: opts.img_type,
+        };
+        self.edit_img_owned_with_metadata(opts).await
+    }
 
-        if let Some(mask) = &opts.mask {
-            let mask_bytes = multipart::Part::bytes(mask.to_owned());
-            form_data = form_data.part("mask".to_owned(), mask_bytes)
-        }
-
-        if let Some(size) = &opts.size {
-            let size_bytes = multipart::Part::bytes(size.to_string().as_bytes().to_owned());
-            form_data = form_data.part("size", size_bytes);
-        }
-
-        if let Some(format) = &opts.response_format {
-            let fmt_bytes = multipart::Part::bytes(format.to_string().as_bytes().to_owned());
-            form_data = form_data.part("response_format", fmt_bytes);
-        }
+    /// Like [`OpenAIClient::edit_img`], but takes `opts` by value so its image and mask can be
+    /// readers.
+    pub async fn edit_img_owned(
+        &self,
+        opts: EditImgOptions,
+    ) -> Result<ImgResponse, Box<dyn Error + Send + Sync>> {
+        Ok(self.edit_img_owned_with_metadata(opts).await?.data)
+    }
 
-        if let Some(user) = &opts.user {
-            let user_bytes = multipart::Part::bytes(user.as_bytes().to_owned());
-            form_data = form_data.part("user", user_bytes);
+    pub async fn edit_img_owned_with_metadata(
+        &self,
+        opts: EditImgOptions,
+    ) -> Result<ApiResponse<ImgResponse>, Box<dyn Error + Send + Sync>> {
+        opts.validate().await?;
+        let img_type = opts.img_type.to_string();
+        let mut form = FormBuilder::new()
+            .file("image", &opts.file_name, Some(&img_type), opts.img)
+            .await?
+            .text("prompt", &opts.prompt);
+        if let Some(mask) = opts.mask {
+            form = form.file("mask", "mask.png", Some(&img_type), mask).await?;
         }
+        let form = form
+            .optional_text("n", opts.n)
+            .optional_text("size", opts.size.as_ref())
+            .optional_text("response_format", opts.response_format)
+            .optional_text("user", opts.user.as_ref());
 
-        let res = self
-            .client
-            .post(&uri)
-            .header("Authorization", format!("Bearer {api_key}"))
-            .multipart(form_data)
-            .send()
-            .await?;
-
-        let images: ImgResponse = res.json().await?;
-        Ok(images)
+        self.post_multipart("/images/edits", form.build()).await
     }
 
+    /// Sends `opts` without consuming it, so its image must be bytes or a path. Use
+    /// [`OpenAIClient::create_img_variations_owned`] for readers.
     pub async fn create_img_variations(
         &self,
         opts: &CreateImgVariationsOptions,
     ) -> Result<ImgResponse, Box<dyn Error + Send + Sync>> {
-        let uri = self.base_uri.clone() + "/images/variations";
-        let api_key = &self.api_key;
-
-        let mut form_data = multipart::Form::new();
-
-        let img = multipart::Part::bytes(opts.img.clone())
-            .file_name(opts.file_name.to_owned())
-            .mime_str(&opts.img_type.to_string())?;
-
-        form_data = form_data.part("image", img);
-
-        if let Some(n) = opts.n {
-            form_data = form_data.text("n", n.to_string());
-        };
-
-        if let Some(size) = &opts.size {
-            let size_bytes = multipart::Part::bytes(size.to_string().as_bytes().to_owned());
-            form_data = form_data.part("size", size_bytes);
-        }
+        Ok(self.create_img_variations_with_metadata(opts).await?.data)
+    }
 
-        if let Some(format) = &opts.response_format {
-            let fmt_bytes = multipart::Part::bytes(format.to_string().as_bytes().to_owned());
-            form_data = form_data.part("response_format", fmt_bytes);
+    pub async fn create_img_variations_with_metadata(
+        &self,
+        opts: &CreateImgVariationsOptions,
+    ) -> Result<ApiResponse<ImgResponse>, Box<dyn Error + Send + Sync>> {
+        let opts = CreateImgVariationsOptions {
+            file_name: opts.file_name.clone(),
+            img: reusable("image", &opts.img)?,
+            n: opts.n,
+            size: opts.size.clone(),
+            response_format: opts.response_format,
+            user: opts.user.clone(),
+            img_type: opts.img_type,
         };
+        self.create_img_variations_owned_with_metadata(opts).await
+    }
 
-        if let Some(user) = &opts.user {
-            let user_bytes = multipart::Part::bytes(user.as_bytes().to_owned());
-            form_data = form_data.part("user", user_bytes);
-        }
+    /// Like [`OpenAIClient::create_img_variations`], but takes `opts` by value so its image can be
+    /// a reader.
+    pub async fn create_img_variations_owned(
+        &self,
+        opts: CreateImgVariationsOptions,
+    ) -> Result<ImgResponse, Box<dyn Error + Send + Sync>> {
+        Ok(self
+            .create_img_variations_owned_with_metadata(opts)
+            .await?
+            .data)
+    }
 
-        let res = self
-            .client
-            .post(&uri)
-            .header("Authorization", format!("Bearer {api_key}"))
-            .multipart(form_data)
-            .send()
-            .await?;
+    pub async fn create_img_variations_owned_with_metadata(
+        &self,
+        opts: CreateImgVariationsOptions,
+    ) -> Result<ApiResponse<ImgResponse>, Box<dyn Error + Send + Sync>> {
+        opts.validate().await?;
+        let img_type = opts.img_type.to_string();
+        let form = FormBuilder::new()
+            .file("image", &opts.file_name, Some(&img_type), opts.img)
+            .await?
+            .optional_text("n", opts.n)
+            .optional_text("size", opts.size.as_ref())
+            .optional_text("response_format", opts.response_format)
+            .optional_text("user", opts.user.as_ref());
 
-        let images = res.json().await?;
+        self.post_multipart("/images/variations", form.build())
+            .await
+    }
+}
 
-        Ok(images)
+/// A copy of `source` to send from options passed by reference.
+fn reusable(
+    part: &str,
+    source: &UploadSource,
+) -> Result<UploadSource, Box<dyn Error + Send + Sync>> {
+    match source.try_clone() {
+        Some(source) => Ok(source),
+        None => Err(format!(
+            "{part} is a reader, which can only be sent once; pass the options by value"
+        )
+        .into()),
     }
 }
 
@@ -274,7 +768,12 @@ impl OpenAIClient {
 mod tests {
     use super::*;
 
+    use crate::openai::multipart::{parse_form, ParsedPart};
+    use crate::openai::png::palette_png;
+    use serde_json::{json, Value};
     use std::{env, sync::Once};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
 
     static INIT: Once = Once::new();
 
@@ -302,6 +801,391 @@ mod tests {
             .expect("error creating image");
     }
 
+    #[test]
+    fn test_img_size_serialization() {
+        let sizes = vec![
+            ImgSize::Size1792x1024,
+            ImgSize::Auto,
+            ImgSize::Custom("2048x2048".to_owned()),
+        ];
+        let json = serde_json::to_value(&sizes).expect("error serializing sizes");
+        assert_eq!(json, json!(["1792x1024", "auto", "2048x2048"]));
+        let parsed: Vec<ImgSize> = serde_json::from_value(json).expect("error parsing sizes");
+        assert_eq!(parsed, sizes);
+    }
+
+    #[test]
+    fn test_img_size_dimensions() {
+        assert_eq!(ImgSize::Size1792x1024.dimensions(), Some((1792, 1024)));
+        assert_eq!(
+            ImgSize::Custom("2048x2048".to_owned()).dimensions(),
+            Some((2048, 2048))
+        );
+        assert_eq!(ImgSize::Custom("huge".to_owned()).dimensions(), None);
+        assert_eq!(ImgSize::Auto.dimensions(), None);
+    }
+
+    #[test]
+    fn test_validate_img_options() {
+        let mut opts = CreateImgOptions::default("a toad on a lily pad");
+        assert!(opts.validate().is_ok());
+
+        opts.model = Some("dall-e-3".to_owned());
+        opts.n = Some(2);
+        let err = opts.validate().expect_err("dall-e-3 allowed n = 2");
+        assert_eq!(err.reason, "n must be between 1 and 1");
+
+        opts.n = Some(1);
+        opts.size = Some(ImgSize::Size1536x1024);
+        assert!(opts.validate().is_err());
+        opts.size = Some(ImgSize::Size1792x1024);
+        opts.quality = Some(ImgQuality::Hd);
+        opts.style = Some(ImgStyle::Natural);
+        assert!(opts.validate().is_ok());
+
+        let mut opts = CreateImgOptions::default("a toad on a lily pad");
+        opts.model = Some("gpt-image-1".to_owned());
+        opts.size = Some(ImgSize::Size1024x1536);
+        opts.background = Some(ImgBackground::Transparent);
+        opts.output_format = Some(ImgOutputFormat::Jpeg);
+        assert!(opts.validate().is_err(), "response_format was allowed");
+        opts.response_format = None;
+        assert!(opts.validate().is_err(), "transparent jpeg was allowed");
+        opts.output_format = Some(ImgOutputFormat::Webp);
+        opts.quality = Some(ImgQuality::Hd);
+        assert!(opts.validate().is_err(), "hd quality was allowed");
+        opts.quality = Some(ImgQuality::High);
+        assert!(opts.validate().is_ok());
+
+        // Newer sizes and models aren't checked
+        opts.size = Some(ImgSize::Custom("4096x4096".to_owned()));
+        assert!(opts.validate().is_ok());
+        opts.model = Some("gpt-image-9".to_owned());
+        opts.style = Some(ImgStyle::Vivid);
+        assert!(opts.validate().is_err());
+        opts.model = Some("future-image-model".to_owned());
+        assert!(opts.validate().is_ok());
+    }
+
+    #[tokio::test]
+    pub async fn test_create_img_dall_e_3() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/images/generations"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "created": 1700000000,
+                "data": [{
+                    "url": "https://example.com/toad.png",
+                    "revised_prompt": "A green toad sitting on a lily pad at dusk"
+                }]
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let mut opts = CreateImgOptions::default("a toad on a lily pad");
+        opts.model = Some("dall-e-3".to_owned());
+        opts.size = Some(ImgSize::Size1024x1792);
+        opts.quality = Some(ImgQuality::Hd);
+        opts.style = Some(ImgStyle::Vivid);
+        let images = client
+            .create_img(&opts)
+            .await
+            .expect("error creating image");
+        assert_eq!(
+            images.data[0].revised_prompt(),
+            Some("A green toad sitting on a lily pad at dusk")
+        );
+
+        let requests = server.received_requests().await.expect("no requests");
+        let body: Value = serde_json::from_slice(&requests[0].body).expect("invalid body");
+        assert_eq!(
+            body,
+            json!({
+                "prompt": "a toad on a lily pad",
+                "model": "dall-e-3",
+                "n": 1,
+                "size": "1024x1792",
+                "quality": "hd",
+                "style": "vivid",
+                "response_format": "url"
+            })
+        );
+
+        // Invalid options are refused before a request is made
+        opts.n = Some(4);
+        let err = client
+            .create_img(&opts)
+            .await
+            .expect_err("dall-e-3 allowed n = 4");
+        assert!(err.downcast_ref::<InvalidImgOptions>().is_some());
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    pub async fn test_save_imgs() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/blob/toad.png"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"url image".to_vec()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let images: ImgResponse = serde_json::from_value(json!({
+            "created": 1700000000,
+            "data": [
+                { "b64_json": STANDARD.encode(b"base64 image") },
+                { "url": format!("{}/blob/toad.png", server.uri()), "revised_prompt": "a toad" }
+            ]
+        }))
+        .expect("error parsing images");
+        assert_eq!(
+            images.data[0].decode().unwrap(),
+            Some(b"base64 image".to_vec())
+        );
+        assert_eq!(images.data[1].decode().unwrap(), None);
+        assert_eq!(images.data[1].revised_prompt(), Some("a toad"));
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let dir = env::temp_dir().join(format!("openai-imgs-{}", std::process::id()));
+        let paths = images
+            .save(&client, &dir)
+            .await
+            .expect("error saving images");
+        assert_eq!(
+            paths,
+            [
+                dir.join("img-1700000000-0.png"),
+                dir.join("img-1700000000-1.png")
+            ]
+        );
+        assert_eq!(std::fs::read(&paths[0]).unwrap(), b"base64 image");
+        assert_eq!(std::fs::read(&paths[1]).unwrap(), b"url image");
+
+        // The API key is only for the API, not for wherever the images are hosted
+        let requests = server.received_requests().await.unwrap();
+        assert!(!requests[0]
+            .headers
+            .iter()
+            .any(|(name, _)| name.as_str() == "authorization"));
+        std::fs::remove_dir_all(dir).expect("error removing directory");
+    }
+
+    /// Narrows a validation result down to the [`InvalidImg`] it failed with.
+    fn invalid(result: Result<(), Box<dyn Error + Send + Sync>>) -> Result<(), InvalidImg> {
+        result.map_err(|err| {
+            err.downcast_ref::<InvalidImg>()
+                .expect("not an InvalidImg")
+                .clone()
+        })
+    }
+
+    #[tokio::test]
+    pub async fn test_validate_edit_img() {
+        let toad = include_bytes!("../../assets/toad.png").to_vec();
+        let transparent = include_bytes!("../../assets/toad-transparent.png").to_vec();
+
+        let mut opts =
+            EditImgOptions::default("toad.png", toad.clone().into(), ImgType::Png, "a hat");
+        assert_eq!(
+            invalid(opts.validate().await),
+            Err(InvalidImg::NoTransparency { part: "image" })
+        );
+        opts.mask = Some(toad.clone().into());
+        assert_eq!(
+            invalid(opts.validate().await),
+            Err(InvalidImg::NoTransparency { part: "mask" })
+        );
+        opts.mask = Some(transparent.clone().into());
+        assert_eq!(invalid(opts.validate().await), Ok(()));
+
+        // Only the header is read, so a header with other dimensions stands in for a smaller mask
+        let mut small_mask = transparent.clone();
+        small_mask[16..24].copy_from_slice(&[0, 0, 1, 0, 0, 0, 1, 0]);
+        opts.mask = Some(small_mask.into());
+        assert_eq!(
+            invalid(opts.validate().await),
+            Err(InvalidImg::MaskSizeMismatch {
+                img: (840, 840),
+                mask: (256, 256)
+            })
+        );
+
+        let mut wide = transparent.clone();
+        wide[16..20].copy_from_slice(&1024u32.to_be_bytes());
+        opts.img = wide.into();
+        opts.mask = None;
+        assert_eq!(
+            invalid(opts.validate().await),
+            Err(InvalidImg::NotSquare {
+                width: 1024,
+                height: 840
+            })
+        );
+
+        // Palette images are transparent if they have a tRNS chunk
+        opts.img = palette_png(true).into();
+        assert_eq!(invalid(opts.validate().await), Ok(()));
+        opts.img = palette_png(false).into();
+        assert_eq!(
+            invalid(opts.validate().await),
+            Err(InvalidImg::NoTransparency { part: "image" })
+        );
+
+        let mut huge = transparent;
+        huge.resize(MAX_IMG_UPLOAD_SIZE, 0);
+        opts.img = huge.clone().into();
+        assert_eq!(invalid(opts.validate().await), Ok(()));
+        huge.push(0);
+        opts.img = huge.into();
+        assert_eq!(
+            invalid(opts.validate().await),
+            Err(InvalidImg::TooLarge {
+                part: "image",
+                size: MAX_IMG_UPLOAD_SIZE + 1
+            })
+        );
+
+        // Readers can't be peeked at, so only their length is checked
+        let jpg = include_bytes!("../../assets/toad.jpg").to_vec();
+        let len = jpg.len() as u64;
+        opts.img = UploadSource::reader(std::io::Cursor::new(jpg.clone()), len);
+        assert_eq!(invalid(opts.validate().await), Ok(()));
+
+        let opts = CreateImgVariationsOptions::default("toad.jpg", jpg.into(), ImgType::Png);
+        assert_eq!(
+            invalid(opts.validate().await),
+            Err(InvalidImg::NotPng { part: "image" })
+        );
+        let path = env::current_dir()
+            .expect("error getting current directory")
+            .join("assets/toad.png");
+        let opts =
+            CreateImgVariationsOptions::default("toad.png", UploadSource::path(path), ImgType::Png);
+        assert_eq!(invalid(opts.validate().await), Ok(()));
+    }
+
+    #[tokio::test]
+    pub async fn test_edit_img_refuses_invalid_img() {
+        let server = MockServer::start().await;
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let jpg = include_bytes!("../../assets/toad.jpg").to_vec();
+        let opts = CreateImgVariationsOptions::default("toad.jpg", jpg.into(), ImgType::Png);
+
+        let err = client
+            .create_img_variations(&opts)
+            .await
+            .expect_err("a JPEG was sent");
+        assert_eq!(
+            err.downcast_ref::<InvalidImg>(),
+            Some(&InvalidImg::NotPng { part: "image" })
+        );
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    pub async fn test_img_forms() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "created": 1700000000,
+                "data": [{ "url": "https://example.com/toad.png" }]
+            })))
+            .mount(&server)
+            .await;
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let toad = include_bytes!("../../assets/toad-transparent.png").to_vec();
+        let file = |name: &str, file_name: &str, body: &[u8]| ParsedPart {
+            name: name.to_owned(),
+            file_name: Some(file_name.to_owned()),
+            content_type: Some("image/png".to_owned()),
+            body: body.to_vec(),
+        };
+
+        let mut opts =
+            EditImgOptions::default("toad.png", toad.clone().into(), ImgType::Png, "a hat");
+        opts.mask = Some(toad.clone().into());
+        opts.user = Some("user-1".to_owned());
+        client.edit_img(&opts).await.expect("error editing image");
+
+        // Files are streamed from disk
+        let path = env::current_dir()
+            .expect("error getting current directory")
+            .join("assets/toad-transparent.png");
+        let opts =
+            CreateImgVariationsOptions::default("toad.png", UploadSource::path(path), ImgType::Png);
+        client
+            .create_img_variations(&opts)
+            .await
+            .expect("error creating image variations");
+
+        let requests = server.received_requests().await.expect("no requests");
+        assert_eq!(requests[0].url.path(), "/images/edits");
+        assert_eq!(
+            parse_form(&requests[0]),
+            [
+                file("image", "toad.png", &toad),
+                ParsedPart::text("prompt", "a hat"),
+                file("mask", "mask.png", &toad),
+                ParsedPart::text("n", "1"),
+                ParsedPart::text("size", "256x256"),
+                ParsedPart::text("response_format", "url"),
+                ParsedPart::text("user", "user-1"),
+            ]
+        );
+        assert_eq!(requests[1].url.path(), "/images/variations");
+        assert_eq!(
+            parse_form(&requests[1]),
+            [
+                file("image", "toad.png", &toad),
+                ParsedPart::text("n", "1"),
+                ParsedPart::text("size", "256x256"),
+                ParsedPart::text("response_format", "url"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_img_forms_from_readers() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/images/variations"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "created": 1700000000,
+                "data": [{ "url": "https://example.com/toad.png" }]
+            })))
+            .mount(&server)
+            .await;
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let toad = include_bytes!("../../assets/toad.png").to_vec();
+        let len = toad.len() as u64;
+        let reader = || UploadSource::reader(std::io::Cursor::new(toad.clone()), len);
+
+        // A reader can't be sent again, so it can't be borrowed from the options
+        let opts = CreateImgVariationsOptions::default("toad.png", reader(), ImgType::Png);
+        let err = client
+            .create_img_variations(&opts)
+            .await
+            .expect_err("a borrowed reader was sent");
+        assert_eq!(
+            err.to_string(),
+            "image is a reader, which can only be sent once; pass the options by value"
+        );
+        let mut edit_opts =
+            EditImgOptions::default("toad.png", toad.clone().into(), ImgType::Png, "a hat");
+        edit_opts.mask = Some(reader());
+        assert!(client.edit_img(&edit_opts).await.is_err());
+        assert!(server.received_requests().await.unwrap().is_empty());
+
+        client
+            .create_img_variations_owned(opts)
+            .await
+            .expect("error creating image variations");
+        let requests = server.received_requests().await.expect("no requests");
+        assert_eq!(parse_form(&requests[0])[0].body, toad);
+    }
+
     #[tokio::test]
     pub async fn test_edit_img() {
         initialize();
@@ -314,11 +1198,9 @@ mod tests {
             .expect("error converting directory path to string")
             + "/assets/toad-transparent.png";
 
-        let img = std::fs::read(&toad_img_path).expect("error loading image");
-
         let opts = EditImgOptions::default(
-            &toad_img_path,
-            img,
+            "toad-transparent.png",
+            UploadSource::path(&toad_img_path),
             ImgType::Png,
             "Please change the background to dark blue",
         );
@@ -339,9 +1221,11 @@ mod tests {
             .expect("error converting directory path to string")
             + "/assets/toad.png";
 
-        let img = std::fs::read(&toad_img_path).expect("error loading image");
-
-        let opts = CreateImgVariationsOptions::default("toad.png", img, ImgType::Png);
+        let opts = CreateImgVariationsOptions::default(
+            "toad.png",
+            UploadSource::path(&toad_img_path),
+            ImgType::Png,
+        );
 
         let images = client
             .create_img_variations(&opts)
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+/// One page of a paginated list endpoint. Pass `last_id` as `after` to fetch the next page.
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListResponse<T> {
+    pub object: String,
+    pub data: Vec<T>,
+    pub first_id: Option<String>,
+    pub last_id: Option<String>,
+    #[serde(default)]
+    pub has_more: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum ListOrder {
+    #[serde(rename = "asc")]
+    Asc,
+    #[serde(rename = "desc")]
+    Desc,
+}
+
+/// Query parameters shared by the paginated list endpoints.
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListOptions {
+    pub limit: Option<u32>,
+    pub order: Option<ListOrder>,
+    pub after: Option<String>,
+    pub before: Option<String>,
+}
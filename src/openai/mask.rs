@@ -0,0 +1,165 @@
+use image::{ImageReader, Rgba, RgbaImage};
+use std::{error::Error, io::Cursor};
+
+use super::preprocess::encode_png;
+
+const KEEP: Rgba<u8> = Rgba([0, 0, 0, 255]);
+const EDIT: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+/// An `edit_img` mask. The edits endpoint only changes the image where the mask is transparent,
+/// so a new mask is opaque and regions are cleared out of it.
+#[derive(Debug, Clone)]
+pub struct ImgMask {
+    mask: RgbaImage,
+}
+
+impl ImgMask {
+    /// An opaque mask of the given size, which leaves the whole image as it is.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            mask: RgbaImage::from_pixel(width, height, KEEP),
+        }
+    }
+
+    /// An opaque mask the size of `img`, which may be any format the `image` feature reads.
+    pub fn for_img(img: &[u8]) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let (width, height) = ImageReader::new(Cursor::new(img))
+            .with_guessed_format()?
+            .into_dimensions()?;
+        Ok(Self::new(width, height))
+    }
+
+    /// A mask that is transparent wherever the alpha of `img` is below `threshold`, for images
+    /// whose background has already been cut out. Cut-out tools often leave the background almost
+    /// but not quite transparent, which the edits endpoint would leave alone.
+    pub fn from_alpha(img: &[u8], threshold: u8) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let img = image::load_from_memory(img)?.to_rgba8();
+        let mask = RgbaImage::from_fn(img.width(), img.height(), |x, y| {
+            if img.get_pixel(x, y)[3] < threshold {
+                EDIT
+            } else {
+                KEEP
+            }
+        });
+        Ok(Self { mask })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.mask.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.mask.height()
+    }
+
+    /// Marks a rectangle for editing. The parts of it outside the mask are ignored.
+    pub fn rect(mut self, x: u32, y: u32, width: u32, height: u32) -> Self {
+        let right = x.saturating_add(width).min(self.width());
+        let bottom = y.saturating_add(height).min(self.height());
+        for py in y..bottom {
+            for px in x..right {
+                self.mask.put_pixel(px, py, EDIT);
+            }
+        }
+        self
+    }
+
+    /// Marks the inside of a polygon for editing, using the even-odd rule. `points` are (x, y) in
+    /// pixels and the last one joins back to the first. A pixel is inside if its centre is, or if
+    /// its centre is on a left edge.
+    pub fn polygon(mut self, points: &[(f32, f32)]) -> Self {
+        if points.len() < 3 {
+            return self;
+        }
+        let max_y = points.iter().map(|(_, y)| *y).fold(f32::MIN, f32::max);
+        let min_y = points.iter().map(|(_, y)| *y).fold(f32::MAX, f32::min);
+        let top = min_y.floor().max(0.0) as u32;
+        let bottom = (max_y.ceil().max(0.0) as u32).min(self.height());
+
+        for py in top..bottom {
+            let cy = py as f32 + 0.5;
+            // Where each edge crosses this row, then fill between pairs of crossings
+            let mut crossings: Vec<f32> = points
+                .iter()
+                .zip(points.iter().cycle().skip(1))
+                .filter(|((_, y1), (_, y2))| (*y1 <= cy) != (*y2 <= cy))
+                .map(|((x1, y1), (x2, y2))| x1 + (cy - y1) / (y2 - y1) * (x2 - x1))
+                .collect();
+            crossings.sort_by(f32::total_cmp);
+
+            for pair in crossings.chunks_exact(2) {
+                let left = (pair[0] - 0.5).ceil().max(0.0) as u32;
+                let right = ((pair[1] - 0.5).ceil().max(0.0) as u32).min(self.width());
+                for px in left..right {
+                    self.mask.put_pixel(px, py, EDIT);
+                }
+            }
+        }
+        self
+    }
+
+    /// Encodes the mask as the RGBA PNG `EditImgOptions::mask` expects.
+    pub fn to_png(&self) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        encode_png(&self.mask)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openai::{
+        images::{EditImgOptions, ImgType},
+        png::read_png_info,
+    };
+
+    fn transparent(mask: &ImgMask) -> Vec<(u32, u32)> {
+        mask.mask
+            .enumerate_pixels()
+            .filter(|(_, _, pixel)| pixel[3] == 0)
+            .map(|(x, y, _)| (x, y))
+            .collect()
+    }
+
+    #[test]
+    fn test_rect_mask() {
+        let toad = include_bytes!("../../assets/toad.jpg");
+        let mask = ImgMask::for_img(toad).expect("error reading image");
+        assert_eq!((mask.width(), mask.height()), (840, 840));
+
+        let mask = ImgMask::new(4, 4).rect(2, 1, 10, 2);
+        assert_eq!(transparent(&mask), [(2, 1), (3, 1), (2, 2), (3, 2)]);
+    }
+
+    #[test]
+    fn test_polygon_mask() {
+        // A right triangle below the diagonal. Centres on the diagonal are on its right edge.
+        let mask = ImgMask::new(4, 4).polygon(&[(0.0, 0.0), (4.0, 4.0), (0.0, 4.0)]);
+        assert_eq!(
+            transparent(&mask),
+            [(0, 1), (0, 2), (1, 2), (0, 3), (1, 3), (2, 3)]
+        );
+
+        // Points outside the mask are clipped
+        let mask = ImgMask::new(2, 2).polygon(&[(-5.0, -5.0), (10.0, -5.0), (10.0, 10.0)]);
+        assert_eq!(transparent(&mask), [(0, 0), (1, 0), (1, 1)]);
+    }
+
+    #[tokio::test]
+    pub async fn test_alpha_mask() {
+        let toad = include_bytes!("../../assets/toad-transparent.png");
+        // The background is at alpha 1, not 0
+        let mask = ImgMask::from_alpha(toad, 1).expect("error reading image");
+        assert!(transparent(&mask).is_empty());
+        let mask = ImgMask::from_alpha(toad, 128).expect("error reading image");
+        let png = mask.to_png().expect("error encoding mask");
+
+        let info = read_png_info(&png).expect("not a PNG");
+        assert_eq!((info.width, info.height, info.has_alpha), (840, 840, true));
+        assert!(!transparent(&mask).is_empty());
+
+        let mut opts =
+            EditImgOptions::default("toad.png", toad.to_vec().into(), ImgType::Png, "a hat");
+        opts.mask = Some(png.into());
+        opts.validate().await.expect("invalid mask");
+    }
+}
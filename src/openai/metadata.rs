@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use reqwest::header::HeaderMap;
+use serde_json::Value;
+
+/// The `x-ratelimit-*` headers of a response. OpenAI sends these on model endpoints; any that are
+/// missing are `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateLimitHeaders {
+    pub limit_requests: Option<u64>,
+    pub limit_tokens: Option<u64>,
+    pub remaining_requests: Option<u64>,
+    pub remaining_tokens: Option<u64>,
+    /// Time until the request limit is back to full
+    pub reset_requests: Option<Duration>,
+    /// Time until the token limit is back to full
+    pub reset_tokens: Option<Duration>,
+}
+
+/// Everything about a response other than its body.
+#[derive(Debug, Clone, Default)]
+pub struct ResponseMetadata {
+    pub status: u16,
+    /// `x-request-id`, useful when contacting OpenAI support
+    pub request_id: Option<String>,
+    /// `openai-processing-ms`
+    pub processing_time: Option<Duration>,
+    /// `openai-model`
+    pub model: Option<String>,
+    /// `openai-organization`
+    pub organization: Option<String>,
+    pub rate_limit: RateLimitHeaders,
+    /// Every header, for anything not parsed above
+    pub headers: HeaderMap,
+}
+
+impl ResponseMetadata {
+    pub fn new(status: u16, headers: &HeaderMap) -> Self {
+        Self {
+            status,
+            request_id: header_str(headers, "x-request-id"),
+            processing_time: header_u64(headers, "openai-processing-ms").map(Duration::from_millis),
+            model: header_str(headers, "openai-model"),
+            organization: header_str(headers, "openai-organization"),
+            rate_limit: RateLimitHeaders {
+                limit_requests: header_u64(headers, "x-ratelimit-limit-requests"),
+                limit_tokens: header_u64(headers, "x-ratelimit-limit-tokens"),
+                remaining_requests: header_u64(headers, "x-ratelimit-remaining-requests"),
+                remaining_tokens: header_u64(headers, "x-ratelimit-remaining-tokens"),
+                reset_requests: header_str(headers, "x-ratelimit-reset-requests")
+                    .and_then(|reset| parse_reset_duration(&reset)),
+                reset_tokens: header_str(headers, "x-ratelimit-reset-tokens")
+                    .and_then(|reset| parse_reset_duration(&reset)),
+            },
+            headers: headers.clone(),
+        }
+    }
+}
+
+/// A response body together with its [`ResponseMetadata`]. Returned by the `*_with_metadata`
+/// variant of each request.
+#[derive(Debug)]
+pub struct ApiResponse<T> {
+    pub data: T,
+    /// The body exactly as OpenAI sent it, including any fields `T` does not model
+    pub raw: Value,
+    pub metadata: ResponseMetadata,
+}
+
+impl<T> ApiResponse<T> {
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+fn header_str(headers: &HeaderMap, name: &str) -> Option<String> {
+    Some(headers.get(name)?.to_str().ok()?.to_owned())
+}
+
+pub(crate) fn header_u64(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.parse().ok()
+}
+
+/// Parses the durations OpenAI sends in `x-ratelimit-reset-*` headers, such as `1s`, `6m0s`,
+/// `20ms` or `1h2m3.5s`.
+fn parse_reset_duration(value: &str) -> Option<Duration> {
+    let mut total = 0.0;
+    let mut rest = value.trim();
+    if rest.is_empty() {
+        return None;
+    }
+
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let number: f64 = rest[..number_len].parse().ok()?;
+        rest = &rest[number_len..];
+
+        let (seconds, unit_len) = if rest.starts_with("ms") {
+            (0.001, 2)
+        } else if rest.starts_with('h') {
+            (3600.0, 1)
+        } else if rest.starts_with('m') {
+            (60.0, 1)
+        } else if rest.starts_with('s') {
+            (1.0, 1)
+        } else {
+            return None;
+        };
+        total += number * seconds;
+        rest = &rest[unit_len..];
+    }
+    Some(Duration::from_secs_f64(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_parse_reset_duration() {
+        assert_eq!(parse_reset_duration("1s"), Some(Duration::from_secs(1)));
+        assert_eq!(
+            parse_reset_duration("20ms"),
+            Some(Duration::from_millis(20))
+        );
+        assert_eq!(parse_reset_duration("6m0s"), Some(Duration::from_secs(360)));
+        assert_eq!(
+            parse_reset_duration("1h2m3.5s"),
+            Some(Duration::from_secs_f64(3723.5))
+        );
+        assert_eq!(parse_reset_duration(""), None);
+        assert_eq!(parse_reset_duration("soon"), None);
+    }
+
+    #[test]
+    fn test_metadata_from_headers() {
+        let mut headers = HeaderMap::new();
+        for (name, value) in [
+            ("x-request-id", "req_123"),
+            ("openai-processing-ms", "412"),
+            ("openai-model", "gpt-3.5-turbo-0613"),
+            ("x-ratelimit-limit-requests", "3500"),
+            ("x-ratelimit-remaining-tokens", "89000"),
+            ("x-ratelimit-reset-requests", "17ms"),
+            ("x-ratelimit-reset-tokens", "1m2s"),
+        ] {
+            headers.insert(name, HeaderValue::from_static(value));
+        }
+
+        let metadata = ResponseMetadata::new(200, &headers);
+        assert_eq!(metadata.request_id.as_deref(), Some("req_123"));
+        assert_eq!(metadata.processing_time, Some(Duration::from_millis(412)));
+        assert_eq!(metadata.model.as_deref(), Some("gpt-3.5-turbo-0613"));
+        assert_eq!(metadata.organization, None);
+        assert_eq!(
+            metadata.rate_limit,
+            RateLimitHeaders {
+                limit_requests: Some(3500),
+                limit_tokens: None,
+                remaining_requests: None,
+                remaining_tokens: Some(89000),
+                reset_requests: Some(Duration::from_millis(17)),
+                reset_tokens: Some(Duration::from_secs(62)),
+            }
+        );
+    }
+}
@@ -1,11 +1,112 @@
+mod assistants;
 mod audio;
+mod batch;
+mod budget;
 mod chat;
 mod client;
 mod completions;
+mod deletion;
 mod edits;
 mod embeddings;
+mod error;
+mod files;
+mod fine_tuning;
 mod images;
+mod list;
+#[cfg(feature = "image")]
+mod mask;
+mod metadata;
 mod models;
+mod moderations;
+mod multipart;
+mod png;
+mod poll;
+#[cfg(feature = "image")]
+mod preprocess;
+mod pricing;
+mod rate_limit;
+mod realtime;
+mod responses;
+mod sse;
+mod tokens;
+mod training_data;
+mod uploads;
 mod usage;
+mod vector_stores;
 
+pub use assistants::{
+    Assistant, AssistantTool, CreateAssistantOptions, CreateMessageOptions, CreateRunOptions,
+    CreateThreadOptions, MessageContent, MessageImageFile, MessageImageUrl, MessageText,
+    ModifyAssistantOptions, ModifyMessageOptions, ModifyThreadOptions, RequiredAction, Run,
+    RunStatus, RunStep, SubmitToolOutputsAction, Thread, ThreadMessage, ToolCall, ToolCallFunction,
+    ToolOutput,
+};
+pub use batch::{
+    Batch, BatchBuilder, BatchEndpoint, BatchRequestCounts, BatchRequestError, BatchResults,
+    BatchStatus, CreateBatchOptions,
+};
+pub use budget::{
+    Budget, BudgetAlert, BudgetExceeded, BudgetLimit, BudgetManager, BudgetReservation, BudgetScope,
+};
+pub use chat::{
+    ChatCompletion, ChatFunction, ChatMessage, ChatOptions, ChatResponseChoice,
+    ChatResponseMessage, ChatRole,
+};
 pub use client::OpenAIClient;
+pub use completions::{Choice, Completion, CompletionOptions};
+pub use deletion::DeletionStatus;
+pub use embeddings::{CreateEmbeddingsOptions, Embedding, Embeddings};
+pub use error::{ApiError, ApiErrorBody};
+pub use files::{FilePurpose, ListFilesOptions, OpenAIFile, UploadFileOptions};
+pub use fine_tuning::{
+    CreateFineTuningJobOptions, FineTuningCheckpoint, FineTuningJob, FineTuningJobEvent,
+    FineTuningJobStatus, Hyperparameters,
+};
+pub use images::{
+    CreateImgOptions, CreateImgVariationsOptions, EditImgOptions, Img, ImgBackground, ImgFormat,
+    ImgOutputFormat, ImgQuality, ImgResponse, ImgSize, ImgStyle, ImgType, InvalidImg,
+    InvalidImgOptions, MAX_IMG_UPLOAD_SIZE,
+};
+pub use list::{ListOptions, ListOrder, ListResponse};
+#[cfg(feature = "image")]
+pub use mask::ImgMask;
+pub use metadata::{ApiResponse, RateLimitHeaders, ResponseMetadata};
+pub use models::{OpenAIGetModelsResponse, OpenAIModel, OpenAIModelPermission};
+pub use moderations::{
+    CreateModerationOptions, InputFlagged, ModerationCategories, ModerationGate,
+    ModerationImageUrl, ModerationInput, ModerationInputPart, ModerationInputType,
+    ModerationResponse, ModerationResult, OutputFlagged,
+};
+pub use multipart::UploadSource;
+pub use poll::{PollOptions, PollTimeout};
+#[cfg(feature = "image")]
+pub use preprocess::{prepare_img, PrepareImgOptions, SquareMode};
+pub use pricing::{ModelPricing, Pricing};
+pub use rate_limit::{RateLimiter, RateLimits};
+pub use realtime::{
+    decode_pcm16, encode_pcm16, RealtimeClientEvent, RealtimeError, RealtimeReceiver,
+    RealtimeResponseConfig, RealtimeSender, RealtimeServerEvent, RealtimeSession,
+    RealtimeSessionConfig,
+};
+pub use responses::{
+    CreateResponseOptions, InputContent, OutputContent, Response, ResponseFormat, ResponseInput,
+    ResponseInputItem, ResponseOutputItem, ResponseStatus, ResponseStreamEvent, ResponseTextConfig,
+    ResponseTool, ResponseUsage,
+};
+pub use training_data::{
+    example_token_limit, validate_dataset, validate_dataset_file, DatasetIssue, DatasetIssueKind,
+    DatasetReport, TrainingDataset, TrainingExample,
+};
+pub use uploads::{
+    CompleteUploadOptions, CreateUploadOptions, LargeUploadOptions, Upload, UploadCancelled,
+    UploadPart, UploadStatus, MAX_UPLOAD_PART_SIZE,
+};
+pub use usage::{Usage, UsageSnapshot, UsageTotals, UsageTracker};
+pub use vector_stores::{
+    ComparisonOperator, CompoundOperator, CreateVectorStoreFileBatchOptions,
+    CreateVectorStoreFileOptions, CreateVectorStoreOptions, ExpiresAfter,
+    ListVectorStoreFilesOptions, ModifyVectorStoreOptions, RankingOptions, SearchFilter,
+    SearchResult, SearchResultContent, SearchResults, SearchVectorStoreOptions, VectorStore,
+    VectorStoreFile, VectorStoreFileBatch, VectorStoreFileCounts, VectorStoreFileStatus,
+    VectorStoreStatus,
+};
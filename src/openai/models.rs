@@ -1,6 +1,10 @@
 use crate::OpenAIClient;
+
+use super::deletion::DeletionStatus;
+use super::metadata::ApiResponse;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::error::Error;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -19,15 +23,28 @@ pub struct OpenAIModelPermission {
     pub is_blocking: bool,
 }
 
+/// `permission`, `root` and `parent` are only returned by older versions of the API. Fields this
+/// struct doesn't know about are kept in `extra`.
+#[serde_with::skip_serializing_none]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OpenAIModel {
     pub id: String,
     pub object: String,
     pub created: i64,
     pub owned_by: String,
-    pub permission: Vec<OpenAIModelPermission>,
-    pub root: String,
-    pub parent: Value,
+    pub permission: Option<Vec<OpenAIModelPermission>>,
+    pub root: Option<String>,
+    pub parent: Option<String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl OpenAIModel {
+    /// Whether this is a model produced by fine-tuning, either `ft:gpt-3.5-turbo:org::id` or the
+    /// legacy `curie:ft-org-2023-01-01` form.
+    pub fn is_fine_tuned(&self) -> bool {
+        self.id.starts_with("ft:") || self.id.contains(":ft-")
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -36,36 +53,78 @@ pub struct OpenAIGetModelsResponse {
     pub data: Vec<OpenAIModel>,
 }
 
+impl OpenAIGetModelsResponse {
+    pub fn owned_by(&self, owner: &str) -> Vec<&OpenAIModel> {
+        self.data
+            .iter()
+            .filter(|model| model.owned_by == owner)
+            .collect()
+    }
+
+    pub fn fine_tuned(&self) -> Vec<&OpenAIModel> {
+        self.data
+            .iter()
+            .filter(|model| model.is_fine_tuned())
+            .collect()
+    }
+
+    pub fn base_models(&self) -> Vec<&OpenAIModel> {
+        self.data
+            .iter()
+            .filter(|model| !model.is_fine_tuned())
+            .collect()
+    }
+
+    /// Models created strictly after `timestamp` (Unix seconds).
+    pub fn created_after(&self, timestamp: i64) -> Vec<&OpenAIModel> {
+        self.data
+            .iter()
+            .filter(|model| model.created > timestamp)
+            .collect()
+    }
+
+    /// Models created strictly before `timestamp` (Unix seconds).
+    pub fn created_before(&self, timestamp: i64) -> Vec<&OpenAIModel> {
+        self.data
+            .iter()
+            .filter(|model| model.created < timestamp)
+            .collect()
+    }
+}
+
 impl OpenAIClient {
     pub async fn get_models(
         &self,
     ) -> Result<OpenAIGetModelsResponse, Box<dyn Error + Send + Sync>> {
-        let uri = self.base_uri.clone() + "/models";
-        let api_key = self.api_key.clone();
-        let res = self
-            .client
-            .get(&uri)
-            .header("Authorization", format!("Bearer {api_key}"))
-            .send()
-            .await?;
-        let models: OpenAIGetModelsResponse = res.json().await?;
-        Ok(models)
+        Ok(self.get_models_with_metadata().await?.data)
+    }
+
+    pub async fn get_models_with_metadata(
+        &self,
+    ) -> Result<ApiResponse<OpenAIGetModelsResponse>, Box<dyn Error + Send + Sync>> {
+        self.get_json("/models").await
     }
 
     pub async fn get_model(
         &self,
         model: &str,
     ) -> Result<OpenAIModel, Box<dyn Error + Send + Sync>> {
-        let uri = self.base_uri.clone() + &format!("/models/{model}");
-        let api_key = self.api_key.clone();
-        let res = self
-            .client
-            .get(&uri)
-            .header("Authorization", format!("Bearer {api_key}"))
-            .send()
-            .await?;
-        let model: OpenAIModel = res.json().await?;
-        Ok(model)
+        Ok(self.get_model_with_metadata(model).await?.data)
+    }
+
+    pub async fn get_model_with_metadata(
+        &self,
+        model: &str,
+    ) -> Result<ApiResponse<OpenAIModel>, Box<dyn Error + Send + Sync>> {
+        self.get_json(&format!("/models/{model}")).await
+    }
+
+    /// Deletes a fine-tuned model. Only the organization that owns a model can delete it.
+    pub async fn delete_model(
+        &self,
+        model: &str,
+    ) -> Result<DeletionStatus, Box<dyn Error + Send + Sync>> {
+        Ok(self.delete_json(&format!("/models/{model}")).await?.data)
     }
 }
 
@@ -74,6 +133,8 @@ mod tests {
     use super::*;
 
     use std::{env, sync::Once};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
 
     static INIT: Once = Once::new();
 
@@ -101,4 +162,137 @@ mod tests {
             .await
             .expect("error fetching model");
     }
+
+    fn models_json() -> Value {
+        let model = |id: &str, created: i64, owned_by: &str| {
+            serde_json::json!({
+                "id": id,
+                "object": "model",
+                "created": created,
+                "owned_by": owned_by,
+                "permission": [],
+                "root": id,
+                "parent": null
+            })
+        };
+        serde_json::json!({
+            "object": "list",
+            "data": [
+                model("gpt-3.5-turbo", 1677610602, "openai"),
+                model("ft:gpt-3.5-turbo-0613:acme::7p4lURel", 1692000000, "acme"),
+                model("curie:ft-acme-2023-01-01-00-00-00", 1672531200, "acme"),
+            ]
+        })
+    }
+
+    fn ids(models: Vec<&OpenAIModel>) -> Vec<String> {
+        models.iter().map(|model| model.id.clone()).collect()
+    }
+
+    #[test]
+    fn test_filter_models() {
+        let models: OpenAIGetModelsResponse =
+            serde_json::from_value(models_json()).expect("error parsing models");
+
+        assert_eq!(ids(models.owned_by("openai")), ["gpt-3.5-turbo"]);
+        assert_eq!(
+            ids(models.fine_tuned()),
+            [
+                "ft:gpt-3.5-turbo-0613:acme::7p4lURel",
+                "curie:ft-acme-2023-01-01-00-00-00"
+            ]
+        );
+        assert_eq!(ids(models.base_models()), ["gpt-3.5-turbo"]);
+        assert_eq!(
+            ids(models.created_after(1680000000)),
+            ["ft:gpt-3.5-turbo-0613:acme::7p4lURel"]
+        );
+        assert_eq!(
+            ids(models.created_before(1677610602)),
+            ["curie:ft-acme-2023-01-01-00-00-00"]
+        );
+    }
+
+    #[test]
+    fn test_deserialize_legacy_models() {
+        let models: OpenAIGetModelsResponse =
+            serde_json::from_str(include_str!("../../assets/fixtures/models_legacy.json"))
+                .expect("error parsing models");
+
+        let davinci = &models.data[0];
+        assert_eq!(davinci.root.as_deref(), Some("text-davinci-003"));
+        assert_eq!(davinci.parent, None);
+        let permission = davinci.permission.as_ref().expect("missing permission");
+        assert!(permission[0].allow_sampling);
+        assert!(davinci.extra.is_empty());
+
+        let fine_tuned = &models.data[1];
+        assert!(fine_tuned.is_fine_tuned());
+        assert_eq!(fine_tuned.parent.as_deref(), Some("curie"));
+    }
+
+    #[test]
+    fn test_deserialize_current_models() {
+        // Entries as GET /models returns them, trimmed, with the fine-tuning organization redacted
+        let models: OpenAIGetModelsResponse =
+            serde_json::from_str(include_str!("../../assets/fixtures/models_current.json"))
+                .expect("error parsing models");
+
+        assert_eq!(models.data.len(), 5);
+        assert!(models.data.iter().all(|model| model.permission.is_none()
+            && model.root.is_none()
+            && model.extra.is_empty()));
+        assert_eq!(
+            ids(models.fine_tuned()),
+            ["ft:gpt-4o-mini-2024-07-18:org-redacted::9uVbYxkz"]
+        );
+        assert_eq!(ids(models.owned_by("openai-internal")), ["whisper-1"]);
+
+        // Fields the API doesn't return aren't serialized back
+        let json = serde_json::to_value(&models.data[0]).expect("error serializing model");
+        assert!(json.get("permission").is_none());
+    }
+
+    #[test]
+    fn test_deserialize_synthetic_unknown_fields() {
+        // Not a recorded response: a model with a field this crate doesn't know about
+        let model: OpenAIModel = serde_json::from_value(serde_json::json!({
+            "id": "toad-1",
+            "object": "model",
+            "created": 1760000000,
+            "owned_by": "system",
+            "capabilities": ["chat", "tools"]
+        }))
+        .expect("error parsing model");
+        assert_eq!(
+            model.extra["capabilities"],
+            serde_json::json!(["chat", "tools"])
+        );
+
+        // Unknown fields survive a round trip
+        let json = serde_json::to_value(&model).expect("error serializing model");
+        assert_eq!(json["capabilities"][1], "tools");
+        assert!(json.get("permission").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_delete_model() {
+        let server = MockServer::start().await;
+        Mock::given(method("DELETE"))
+            .and(path("/models/ft:gpt-3.5-turbo-0613:acme::7p4lURel"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "ft:gpt-3.5-turbo-0613:acme::7p4lURel",
+                "object": "model",
+                "deleted": true
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let deleted = client
+            .delete_model("ft:gpt-3.5-turbo-0613:acme::7p4lURel")
+            .await
+            .expect("error deleting model");
+        assert!(deleted.deleted);
+    }
 }
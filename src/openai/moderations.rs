@@ -0,0 +1,525 @@
+use core::fmt;
+use std::error::Error;
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::OpenAIClient;
+
+use super::chat::ChatCompletion;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModerationImageUrl {
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ModerationInputPart {
+    #[serde(rename = "text")]
+    Text { text: String },
+    #[serde(rename = "image_url")]
+    ImageUrl { image_url: ModerationImageUrl },
+}
+
+impl ModerationInputPart {
+    pub fn text(text: &str) -> Self {
+        ModerationInputPart::Text {
+            text: text.to_owned(),
+        }
+    }
+
+    /// `url` may be a web URL or a base64 `data:` URL.
+    pub fn image_url(url: &str) -> Self {
+        ModerationInputPart::ImageUrl {
+            image_url: ModerationImageUrl {
+                url: url.to_owned(),
+            },
+        }
+    }
+}
+
+/// Images are only accepted by the `omni-moderation` models.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ModerationInput {
+    Text(String),
+    Texts(Vec<String>),
+    Multimodal(Vec<ModerationInputPart>),
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateModerationOptions {
+    pub input: ModerationInput,
+    pub model: Option<String>,
+}
+
+impl CreateModerationOptions {
+    pub fn default(input: ModerationInput) -> Self {
+        Self { input, model: None }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ModerationInputType {
+    #[serde(rename = "text")]
+    Text,
+    #[serde(rename = "image")]
+    Image,
+}
+
+/// One value per moderation category: whether it was flagged, its score, or the input types it
+/// was applied to. The `illicit` categories are only returned by the `omni-moderation` models.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModerationCategories<T> {
+    pub harassment: T,
+    #[serde(rename = "harassment/threatening")]
+    pub harassment_threatening: T,
+    pub hate: T,
+    #[serde(rename = "hate/threatening")]
+    pub hate_threatening: T,
+    #[serde(default)]
+    pub illicit: Option<T>,
+    #[serde(rename = "illicit/violent", default)]
+    pub illicit_violent: Option<T>,
+    #[serde(rename = "self-harm")]
+    pub self_harm: T,
+    #[serde(rename = "self-harm/intent")]
+    pub self_harm_intent: T,
+    #[serde(rename = "self-harm/instructions")]
+    pub self_harm_instructions: T,
+    pub sexual: T,
+    #[serde(rename = "sexual/minors")]
+    pub sexual_minors: T,
+    pub violence: T,
+    #[serde(rename = "violence/graphic")]
+    pub violence_graphic: T,
+}
+
+impl<T: Clone> ModerationCategories<T> {
+    /// Every category the model returned, by its API name.
+    pub fn to_vec(&self) -> Vec<(&'static str, T)> {
+        let mut categories = vec![
+            ("harassment", self.harassment.clone()),
+            (
+                "harassment/threatening",
+                self.harassment_threatening.clone(),
+            ),
+            ("hate", self.hate.clone()),
+            ("hate/threatening", self.hate_threatening.clone()),
+            ("self-harm", self.self_harm.clone()),
+            ("self-harm/intent", self.self_harm_intent.clone()),
+            (
+                "self-harm/instructions",
+                self.self_harm_instructions.clone(),
+            ),
+            ("sexual", self.sexual.clone()),
+            ("sexual/minors", self.sexual_minors.clone()),
+            ("violence", self.violence.clone()),
+            ("violence/graphic", self.violence_graphic.clone()),
+        ];
+        if let Some(illicit) = &self.illicit {
+            categories.push(("illicit", illicit.clone()));
+        }
+        if let Some(illicit_violent) = &self.illicit_violent {
+            categories.push(("illicit/violent", illicit_violent.clone()));
+        }
+        categories
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModerationResult {
+    pub flagged: bool,
+    pub categories: ModerationCategories<bool>,
+    pub category_scores: ModerationCategories<f64>,
+    /// Only returned by the `omni-moderation` models
+    pub category_applied_input_types: Option<ModerationCategories<Vec<ModerationInputType>>>,
+}
+
+impl ModerationResult {
+    /// The API names of the categories this result was flagged for.
+    pub fn flagged_categories(&self) -> Vec<&'static str> {
+        self.categories
+            .to_vec()
+            .into_iter()
+            .filter(|(_, flagged)| *flagged)
+            .map(|(category, _)| category)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModerationResponse {
+    pub id: String,
+    pub model: String,
+    pub results: Vec<ModerationResult>,
+}
+
+impl ModerationResponse {
+    /// Whether any of the inputs were flagged.
+    pub fn is_flagged(&self) -> bool {
+        self.results.iter().any(|result| result.flagged)
+    }
+}
+
+/// Runs chat completions through moderation. The latest user message is moderated before the
+/// request is sent and, if `moderate_output` is set, every returned choice is moderated after.
+#[derive(Debug, Clone, Default)]
+pub struct ModerationGate {
+    /// The moderation model, or the API's default if `None`
+    pub model: Option<String>,
+    pub moderate_output: bool,
+}
+
+/// Returned instead of sending a chat request whose latest user message was flagged.
+#[derive(Debug, Clone)]
+pub struct InputFlagged {
+    pub input: String,
+    pub result: ModerationResult,
+}
+
+impl Display for InputFlagged {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "request refused: input was flagged by moderation for {}",
+            self.result.flagged_categories().join(", ")
+        )
+    }
+}
+
+impl Error for InputFlagged {}
+
+/// Returned when a chat completion was generated, and paid for, but one of its choices was
+/// flagged.
+#[derive(Debug, Clone)]
+pub struct OutputFlagged {
+    /// Index of the flagged choice
+    pub index: usize,
+    pub output: String,
+    pub result: ModerationResult,
+    /// The whole completion, including its usage, for logging or auditing
+    pub completion: ChatCompletion,
+}
+
+impl Display for OutputFlagged {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "completion choice {} was flagged by moderation for {}",
+            self.index,
+            self.result.flagged_categories().join(", ")
+        )
+    }
+}
+
+impl Error for OutputFlagged {}
+
+impl OpenAIClient {
+    pub async fn create_moderation(
+        &self,
+        opts: &CreateModerationOptions,
+    ) -> Result<ModerationResponse, Box<dyn Error + Send + Sync>> {
+        Ok(self.post_json("/moderations", opts).await?.data)
+    }
+
+    pub(crate) async fn moderate_input(
+        &self,
+        input: &str,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(gate) = &self.moderation else {
+            return Ok(());
+        };
+        let opts = CreateModerationOptions {
+            input: ModerationInput::Text(input.to_owned()),
+            model: gate.model.clone(),
+        };
+        let moderation = self.create_moderation(&opts).await?;
+        match moderation.results.into_iter().find(|result| result.flagged) {
+            Some(result) => Err(Box::new(InputFlagged {
+                input: input.to_owned(),
+                result,
+            })),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns `completion` unless one of its choices is flagged.
+    pub(crate) async fn moderate_outputs(
+        &self,
+        completion: ChatCompletion,
+    ) -> Result<ChatCompletion, Box<dyn Error + Send + Sync>> {
+        match &self.moderation {
+            Some(gate) if gate.moderate_output && !completion.choices.is_empty() => {
+                let outputs: Vec<String> = completion
+                    .choices
+                    .iter()
+                    .map(|choice| choice.message.content.clone())
+                    .collect();
+                let opts = CreateModerationOptions {
+                    input: ModerationInput::Texts(outputs.clone()),
+                    model: gate.model.clone(),
+                };
+                let moderation = self.create_moderation(&opts).await?;
+                let flagged = moderation
+                    .results
+                    .into_iter()
+                    .zip(outputs)
+                    .enumerate()
+                    .find(|(_, (result, _))| result.flagged);
+                match flagged {
+                    Some((index, (result, output))) => Err(Box::new(OutputFlagged {
+                        index,
+                        output,
+                        result,
+                        completion,
+                    })),
+                    None => Ok(completion),
+                }
+            }
+            _ => Ok(completion),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::openai::chat::{ChatMessage, ChatOptions, ChatRole};
+    use serde_json::{json, Value};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn categories_json(value: Value) -> Value {
+        json!({
+            "harassment": value,
+            "harassment/threatening": value,
+            "hate": value,
+            "hate/threatening": value,
+            "illicit": value,
+            "illicit/violent": value,
+            "self-harm": value,
+            "self-harm/intent": value,
+            "self-harm/instructions": value,
+            "sexual": value,
+            "sexual/minors": value,
+            "violence": value,
+            "violence/graphic": value
+        })
+    }
+
+    #[tokio::test]
+    pub async fn test_create_moderation() {
+        let mut categories = categories_json(json!(false));
+        categories["violence"] = json!(true);
+        categories["violence/graphic"] = json!(true);
+        let mut applied = categories_json(json!(["text"]));
+        applied["violence"] = json!(["text", "image"]);
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/moderations"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "modr-1",
+                "model": "omni-moderation-latest",
+                "results": [{
+                    "flagged": true,
+                    "categories": categories,
+                    "category_scores": categories_json(json!(0.25)),
+                    "category_applied_input_types": applied
+                }]
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let mut opts = CreateModerationOptions::default(ModerationInput::Multimodal(vec![
+            ModerationInputPart::text("the toad stood over its prey"),
+            ModerationInputPart::image_url("https://example.com/toad.png"),
+        ]));
+        opts.model = Some("omni-moderation-latest".to_owned());
+        let moderation = client
+            .create_moderation(&opts)
+            .await
+            .expect("error creating moderation");
+
+        assert!(moderation.is_flagged());
+        let result = &moderation.results[0];
+        assert_eq!(
+            result.flagged_categories(),
+            ["violence", "violence/graphic"]
+        );
+        assert_eq!(result.category_scores.illicit, Some(0.25));
+        let applied = result
+            .category_applied_input_types
+            .as_ref()
+            .expect("missing applied input types");
+        assert_eq!(
+            applied.violence,
+            [ModerationInputType::Text, ModerationInputType::Image]
+        );
+
+        let requests = server.received_requests().await.expect("no requests");
+        let body: Value = serde_json::from_slice(&requests[0].body).expect("invalid body");
+        assert_eq!(
+            body,
+            json!({
+                "model": "omni-moderation-latest",
+                "input": [
+                    { "type": "text", "text": "the toad stood over its prey" },
+                    { "type": "image_url", "image_url": { "url": "https://example.com/toad.png" } }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_legacy_moderation_result() {
+        let mut categories = categories_json(json!(false));
+        categories["illicit"] = Value::Null;
+        let mut scores = categories_json(json!(0.01));
+        scores.as_object_mut().unwrap().remove("illicit");
+        scores.as_object_mut().unwrap().remove("illicit/violent");
+
+        let result: ModerationResult = serde_json::from_value(json!({
+            "flagged": false,
+            "categories": categories,
+            "category_scores": scores
+        }))
+        .expect("error parsing moderation result");
+
+        assert!(result.flagged_categories().is_empty());
+        assert_eq!(result.categories.illicit, None);
+        assert_eq!(result.category_scores.to_vec().len(), 11);
+        assert!(result.category_applied_input_types.is_none());
+    }
+
+    fn moderation_json(flags: &[bool]) -> Value {
+        let results: Vec<Value> = flags
+            .iter()
+            .map(|flagged| {
+                json!({
+                    "flagged": flagged,
+                    "categories": categories_json(json!(flagged)),
+                    "category_scores": categories_json(json!(if *flagged { 0.9 } else { 0.01 }))
+                })
+            })
+            .collect();
+        json!({ "id": "modr-1", "model": "omni-moderation-latest", "results": results })
+    }
+
+    fn chat_json() -> Value {
+        json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1690000000,
+            "model": "gpt-3.5-turbo-0613",
+            "choices": [
+                { "index": 0, "message": { "role": "assistant", "content": "Ribbit." }, "finish_reason": "stop" },
+                { "index": 1, "message": { "role": "assistant", "content": "RIBBIT!" }, "finish_reason": "stop" }
+            ],
+            "usage": { "prompt_tokens": 9, "completion_tokens": 6, "total_tokens": 15 }
+        })
+    }
+
+    fn chat_opts(content: &str) -> ChatOptions {
+        let mut opts = ChatOptions::default(
+            "gpt-3.5-turbo",
+            vec![
+                ChatMessage::new(ChatRole::System, "you are a toad"),
+                ChatMessage::new(ChatRole::User, content),
+            ],
+            20,
+        );
+        opts.n = Some(2);
+        opts
+    }
+
+    #[tokio::test]
+    pub async fn test_moderation_gate_flags_input() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/moderations"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(moderation_json(&[true])))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(chat_json()))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let err = client
+            .with_moderation(ModerationGate::default())
+            .get_chat_completion(&chat_opts("something awful"))
+            .await
+            .expect_err("flagged input was sent");
+        let flagged = err
+            .downcast_ref::<InputFlagged>()
+            .expect("expected InputFlagged");
+        assert_eq!(flagged.input, "something awful");
+        assert_eq!(flagged.result.flagged_categories().len(), 13);
+
+        let requests = server.received_requests().await.expect("no requests");
+        let body: Value = serde_json::from_slice(&requests[0].body).expect("invalid body");
+        assert_eq!(body, json!({ "input": "something awful" }));
+    }
+
+    #[tokio::test]
+    pub async fn test_moderation_gate_flags_output() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/moderations"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(moderation_json(&[false])))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/moderations"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(moderation_json(&[false, true])))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(chat_json()))
+            .mount(&server)
+            .await;
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let gate = ModerationGate {
+            model: Some("omni-moderation-latest".to_owned()),
+            moderate_output: true,
+        };
+        let err = client
+            .with_moderation(gate)
+            .get_chat_completion(&chat_opts("say hi"))
+            .await
+            .expect_err("flagged output was returned");
+        let flagged = err
+            .downcast_ref::<OutputFlagged>()
+            .expect("expected OutputFlagged");
+        assert_eq!(flagged.index, 1);
+        assert_eq!(flagged.output, "RIBBIT!");
+        assert_eq!(flagged.completion.choices[1].message.content, "RIBBIT!");
+        assert_eq!(flagged.completion.usage.total_tokens, 15);
+
+        let requests = server.received_requests().await.expect("no requests");
+        let body: Value = serde_json::from_slice(&requests[2].body).expect("invalid body");
+        assert_eq!(body["input"], json!(["Ribbit.", "RIBBIT!"]));
+        assert_eq!(body["model"], "omni-moderation-latest");
+
+        // Without the gate nothing is moderated
+        let completion = client
+            .get_chat_completion(&chat_opts("say hi"))
+            .await
+            .expect("error fetching chat completion");
+        assert_eq!(completion.choices.len(), 2);
+        let requests = server.received_requests().await.expect("no requests");
+        assert_eq!(requests.len(), 4);
+    }
+}
@@ -0,0 +1,346 @@
+use bytes::Bytes;
+use reqwest::{
+    multipart::{Form, Part},
+    Body,
+};
+use std::{error::Error, fmt, fmt::Display, path::PathBuf};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio_util::io::ReaderStream;
+
+/// How much of a file [`UploadSource::head`] reads
+const HEAD_LEN: u64 = 64 * 1024;
+
+/// The contents of a file part in a multipart upload. `Path` and `Reader` are streamed into the
+/// request rather than read into memory, and cloning `Bytes` doesn't copy the data.
+pub enum UploadSource {
+    Bytes(Bytes),
+    /// Opened when the request is sent
+    Path(PathBuf),
+    /// Read once, so requests that take it can't be retried. `len` must be exact.
+    Reader {
+        reader: Box<dyn AsyncRead + Send + Sync + Unpin>,
+        len: u64,
+    },
+}
+
+impl UploadSource {
+    pub fn path(path: impl Into<PathBuf>) -> Self {
+        UploadSource::Path(path.into())
+    }
+
+    pub fn reader(reader: impl AsyncRead + Send + Sync + Unpin + 'static, len: u64) -> Self {
+        UploadSource::Reader {
+            reader: Box::new(reader),
+            len,
+        }
+    }
+
+    /// The data, if it is already in memory.
+    pub fn as_bytes(&self) -> Option<&Bytes> {
+        match self {
+            UploadSource::Bytes(bytes) => Some(bytes),
+            UploadSource::Path(_) | UploadSource::Reader { .. } => None,
+        }
+    }
+
+    /// Another source with the same data, or `None` for readers, which can only be read once. A
+    /// path is copied, not the file it points to.
+    pub fn try_clone(&self) -> Option<Self> {
+        match self {
+            UploadSource::Bytes(bytes) => Some(UploadSource::Bytes(bytes.clone())),
+            UploadSource::Path(path) => Some(UploadSource::Path(path.clone())),
+            UploadSource::Reader { .. } => None,
+        }
+    }
+
+    /// In bytes. Files are measured without being read.
+    pub async fn size(&self) -> Result<u64, Box<dyn Error + Send + Sync>> {
+        Ok(match self {
+            UploadSource::Bytes(bytes) => bytes.len() as u64,
+            UploadSource::Path(path) => tokio::fs::metadata(path).await?.len(),
+            UploadSource::Reader { len, .. } => *len,
+        })
+    }
+
+    /// All of the data in memory, or the first 64 KiB of a file, for checking headers before
+    /// sending. `None` for readers, which can only be read once.
+    pub(crate) async fn head(&self) -> Result<Option<Bytes>, Box<dyn Error + Send + Sync>> {
+        match self {
+            UploadSource::Bytes(bytes) => Ok(Some(bytes.clone())),
+            UploadSource::Path(path) => {
+                let mut head = vec![];
+                tokio::fs::File::open(path)
+                    .await?
+                    .take(HEAD_LEN)
+                    .read_to_end(&mut head)
+                    .await?;
+                Ok(Some(head.into()))
+            }
+            UploadSource::Reader { .. } => Ok(None),
+        }
+    }
+
+    async fn into_part(self) -> Result<Part, Box<dyn Error + Send + Sync>> {
+        let (body, len) = match self {
+            UploadSource::Bytes(bytes) => {
+                let len = bytes.len() as u64;
+                (Body::from(bytes), len)
+            }
+            UploadSource::Path(path) => {
+                let file = tokio::fs::File::open(path).await?;
+                let len = file.metadata().await?.len();
+                (Body::wrap_stream(ReaderStream::new(file)), len)
+            }
+            UploadSource::Reader { reader, len } => {
+                (Body::wrap_stream(ReaderStream::new(reader)), len)
+            }
+        };
+        Ok(Part::stream_with_length(body, len))
+    }
+}
+
+impl fmt::Debug for UploadSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UploadSource::Bytes(bytes) => f.debug_tuple("Bytes").field(&bytes.len()).finish(),
+            UploadSource::Path(path) => f.debug_tuple("Path").field(path).finish(),
+            UploadSource::Reader { len, .. } => f
+                .debug_struct("Reader")
+                .field("len", len)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+impl From<Bytes> for UploadSource {
+    fn from(bytes: Bytes) -> Self {
+        UploadSource::Bytes(bytes)
+    }
+}
+
+impl From<Vec<u8>> for UploadSource {
+    fn from(bytes: Vec<u8>) -> Self {
+        UploadSource::Bytes(bytes.into())
+    }
+}
+
+/// Builds `multipart/form-data` bodies the way strict servers expect them: plain values as text
+/// fields, and files as parts with a file name and a content type.
+pub(crate) struct FormBuilder {
+    form: Form,
+}
+
+impl FormBuilder {
+    pub(crate) fn new() -> Self {
+        Self { form: Form::new() }
+    }
+
+    pub(crate) fn text(mut self, name: &str, value: impl Display) -> Self {
+        self.form = self.form.text(name.to_owned(), value.to_string());
+        self
+    }
+
+    /// Adds a text field if `value` is set.
+    pub(crate) fn optional_text(self, name: &str, value: Option<impl Display>) -> Self {
+        match value {
+            Some(value) => self.text(name, value),
+            None => self,
+        }
+    }
+
+    /// Adds a file part. Paths are opened here, but nothing is read until the request is sent.
+    pub(crate) async fn file(
+        mut self,
+        name: &str,
+        file_name: &str,
+        content_type: Option<&str>,
+        source: UploadSource,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let mut part = source.into_part().await?.file_name(file_name.to_owned());
+        if let Some(content_type) = content_type {
+            part = part.mime_str(content_type)?;
+        }
+        self.form = self.form.part(name.to_owned(), part);
+        Ok(self)
+    }
+
+    pub(crate) fn build(self) -> Form {
+        self.form
+    }
+}
+
+/// One part of a form body as the server sees it
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ParsedPart {
+    pub(crate) name: String,
+    pub(crate) file_name: Option<String>,
+    pub(crate) content_type: Option<String>,
+    pub(crate) body: Vec<u8>,
+}
+
+#[cfg(test)]
+impl ParsedPart {
+    /// A text field, which has neither a file name nor a content type.
+    pub(crate) fn text(name: &str, value: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            file_name: None,
+            content_type: None,
+            body: value.as_bytes().to_vec(),
+        }
+    }
+}
+
+/// Splits a `multipart/form-data` request received by a mock server into its parts.
+#[cfg(test)]
+pub(crate) fn parse_form(request: &wiremock::Request) -> Vec<ParsedPart> {
+    let content_type = request
+        .headers
+        .iter()
+        .find(|(name, _)| name.as_str() == "content-type")
+        .map(|(_, values)| values.last().as_str().to_owned())
+        .expect("no content type");
+    let boundary = content_type.split_once("boundary=").expect("no boundary").1;
+    let delimiter = format!("--{boundary}");
+    let delimiter = delimiter.as_bytes();
+
+    let mut parts = vec![];
+    let mut body = &request.body[..];
+    loop {
+        let start = find(body, delimiter).expect("missing boundary") + delimiter.len();
+        body = &body[start..];
+        if body.starts_with(b"--") {
+            return parts;
+        }
+        let end = find(body, delimiter).expect("unterminated part");
+        // Each part is "\r\n{headers}\r\n\r\n{body}\r\n"
+        let part = &body[2..end - 2];
+        let split = find(part, b"\r\n\r\n").expect("no part headers");
+        let headers = String::from_utf8_lossy(&part[..split]);
+
+        let mut parsed = ParsedPart {
+            name: String::new(),
+            file_name: None,
+            content_type: None,
+            body: part[split + 4..].to_vec(),
+        };
+        for header in headers.lines() {
+            let (header, value) = header.split_once(": ").expect("invalid part header");
+            match header.to_lowercase().as_str() {
+                "content-disposition" => {
+                    for param in value.split("; ").skip(1) {
+                        let (key, value) = param.split_once('=').expect("invalid parameter");
+                        let value = value.trim_matches('"').to_owned();
+                        match key {
+                            "name" => parsed.name = value,
+                            "filename" => parsed.file_name = Some(value),
+                            _ => {}
+                        }
+                    }
+                }
+                "content-type" => parsed.content_type = Some(value.to_owned()),
+                _ => {}
+            }
+        }
+        parts.push(parsed);
+        body = &body[end..];
+    }
+}
+
+#[cfg(test)]
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    pub async fn test_form_builder() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let form = FormBuilder::new()
+            .text("prompt", "a toad")
+            .optional_text("n", Some(2))
+            .optional_text("user", None::<String>)
+            .file(
+                "image",
+                "toad.png",
+                Some("image/png"),
+                b"\x89PNG\r\n--".to_vec().into(),
+            )
+            .await
+            .expect("invalid content type")
+            .file(
+                "data",
+                "data",
+                None,
+                UploadSource::reader(Cursor::new(b"ribbit".to_vec()), 6),
+            )
+            .await
+            .expect("error adding reader")
+            .build();
+        reqwest::Client::new()
+            .post(server.uri())
+            .multipart(form)
+            .send()
+            .await
+            .expect("error sending form");
+
+        let requests = server.received_requests().await.expect("no requests");
+        assert_eq!(
+            parse_form(&requests[0]),
+            [
+                ParsedPart::text("prompt", "a toad"),
+                ParsedPart::text("n", "2"),
+                ParsedPart {
+                    name: "image".to_owned(),
+                    file_name: Some("toad.png".to_owned()),
+                    content_type: Some("image/png".to_owned()),
+                    body: b"\x89PNG\r\n--".to_vec(),
+                },
+                ParsedPart {
+                    name: "data".to_owned(),
+                    file_name: Some("data".to_owned()),
+                    content_type: None,
+                    body: b"ribbit".to_vec(),
+                }
+            ]
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_upload_source_head() {
+        let path = std::env::current_dir()
+            .expect("error getting current directory")
+            .join("assets/toad.png");
+        let toad = std::fs::read(&path).expect("error loading image");
+
+        let source = UploadSource::path(&path);
+        assert_eq!(source.size().await.unwrap(), toad.len() as u64);
+        let head = source.head().await.unwrap().expect("no head");
+        assert_eq!(&head[..], &toad[..HEAD_LEN as usize]);
+
+        let source = UploadSource::from(toad.clone());
+        assert_eq!(
+            source.head().await.unwrap().expect("no head").len(),
+            toad.len()
+        );
+        assert!(source.try_clone().is_some());
+        let source = UploadSource::reader(Cursor::new(toad), 10);
+        assert!(source.try_clone().is_none());
+        assert_eq!(source.size().await.unwrap(), 10);
+        assert!(source.head().await.unwrap().is_none());
+    }
+}
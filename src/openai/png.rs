@@ -0,0 +1,97 @@
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// What the image endpoints care about in a PNG, read from its chunk headers without decoding
+/// the pixels. Chunk CRCs are not checked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct PngInfo {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// A colour type with an alpha channel, or a `tRNS` transparency chunk
+    pub(crate) has_alpha: bool,
+}
+
+/// Returns `None` if `bytes` doesn't start with a PNG signature followed by an `IHDR` chunk.
+pub(crate) fn read_png_info(bytes: &[u8]) -> Option<PngInfo> {
+    if bytes.get(..8)? != SIGNATURE || bytes.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(bytes.get(16..20)?.try_into().ok()?);
+    let height = u32::from_be_bytes(bytes.get(20..24)?.try_into().ok()?);
+    let color_type = *bytes.get(25)?;
+
+    // Grayscale + alpha and RGBA
+    let mut has_alpha = matches!(color_type, 4 | 6);
+    let mut offset = 8;
+    while !has_alpha {
+        let Some(header) = bytes.get(offset..offset + 8) else {
+            break;
+        };
+        let length = u32::from_be_bytes(header[..4].try_into().ok()?) as usize;
+        match &header[4..] {
+            b"tRNS" => has_alpha = true,
+            b"IDAT" | b"IEND" => break,
+            _ => {}
+        }
+        // Length, type, data and CRC
+        offset += 12 + length;
+    }
+
+    Some(PngInfo {
+        width,
+        height,
+        has_alpha,
+    })
+}
+
+/// A 2x2 palette PNG, with a `tRNS` chunk making its first colour transparent if `transparent`
+/// is set. The pixel data is left empty, since only the chunk headers are read.
+#[cfg(test)]
+pub(crate) fn palette_png(transparent: bool) -> Vec<u8> {
+    fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        png.extend_from_slice(kind);
+        png.extend_from_slice(data);
+        // CRC, which isn't checked
+        png.extend_from_slice(&[0; 4]);
+    }
+
+    let mut png = SIGNATURE.to_vec();
+    // 2x2, 8 bits per index, colour type 3 (palette)
+    chunk(&mut png, b"IHDR", &[0, 0, 0, 2, 0, 0, 0, 2, 8, 3, 0, 0, 0]);
+    chunk(&mut png, b"PLTE", &[40, 160, 60, 255, 255, 255]);
+    if transparent {
+        chunk(&mut png, b"tRNS", &[0]);
+    }
+    chunk(&mut png, b"IDAT", &[]);
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_png_info() {
+        let toad = read_png_info(include_bytes!("../../assets/toad.png")).expect("not a PNG");
+        assert_eq!(
+            toad,
+            PngInfo {
+                width: 840,
+                height: 840,
+                has_alpha: false
+            }
+        );
+        let transparent =
+            read_png_info(include_bytes!("../../assets/toad-transparent.png")).expect("not a PNG");
+        assert!(transparent.has_alpha);
+
+        let palette = read_png_info(&palette_png(true)).expect("not a PNG");
+        assert_eq!((palette.width, palette.height), (2, 2));
+        assert!(palette.has_alpha);
+        assert!(!read_png_info(&palette_png(false)).unwrap().has_alpha);
+
+        assert_eq!(read_png_info(include_bytes!("../../assets/toad.jpg")), None);
+        assert_eq!(read_png_info(&SIGNATURE), None);
+    }
+}
@@ -0,0 +1,117 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::time::{Duration, Instant};
+
+/// How long-running operations such as fine-tuning jobs are polled. The interval starts at
+/// `initial_interval` and is multiplied by `multiplier` after every poll, up to `max_interval`.
+/// A multiplier that is negative, NaN or infinite jumps straight to `max_interval`.
+#[derive(Debug, Clone)]
+pub struct PollOptions {
+    pub initial_interval: Duration,
+    pub max_interval: Duration,
+    pub multiplier: f64,
+    /// Give up with a [`PollTimeout`] after this long, or never if `None`
+    pub timeout: Option<Duration>,
+}
+
+impl Default for PollOptions {
+    fn default() -> Self {
+        Self {
+            initial_interval: Duration::from_secs(1),
+            max_interval: Duration::from_secs(30),
+            multiplier: 1.5,
+            timeout: None,
+        }
+    }
+}
+
+/// Returned (boxed) when an operation has not finished within [`PollOptions::timeout`].
+#[derive(Debug, Clone)]
+pub struct PollTimeout {
+    pub elapsed: Duration,
+}
+
+impl Display for PollTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "operation did not finish within {:?}", self.elapsed)
+    }
+}
+
+impl Error for PollTimeout {}
+
+pub(crate) struct Backoff<'a> {
+    opts: &'a PollOptions,
+    interval: Duration,
+    started: Instant,
+}
+
+impl<'a> Backoff<'a> {
+    pub(crate) fn new(opts: &'a PollOptions) -> Self {
+        Self {
+            opts,
+            interval: opts.initial_interval,
+            started: Instant::now(),
+        }
+    }
+
+    /// Sleeps until the next poll is due.
+    pub(crate) async fn wait(&mut self) -> Result<(), PollTimeout> {
+        let elapsed = self.started.elapsed();
+        if let Some(timeout) = self.opts.timeout {
+            if elapsed.saturating_add(self.interval) > timeout {
+                return Err(PollTimeout { elapsed });
+            }
+        }
+        tokio::time::sleep(self.interval).await;
+        self.interval = next_interval(self.interval, self.opts);
+        Ok(())
+    }
+}
+
+fn next_interval(interval: Duration, opts: &PollOptions) -> Duration {
+    Duration::try_from_secs_f64(interval.as_secs_f64() * opts.multiplier)
+        .unwrap_or(opts.max_interval)
+        .min(opts.max_interval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_backoff_timeout() {
+        let opts = PollOptions {
+            initial_interval: Duration::from_millis(10),
+            max_interval: Duration::from_millis(20),
+            multiplier: 2.0,
+            timeout: Some(Duration::from_millis(45)),
+        };
+        let mut backoff = Backoff::new(&opts);
+        backoff
+            .wait()
+            .await
+            .expect("10ms should fit in the timeout");
+        backoff
+            .wait()
+            .await
+            .expect("30ms should fit in the timeout");
+        assert_eq!(backoff.interval, Duration::from_millis(20));
+        backoff
+            .wait()
+            .await
+            .expect_err("50ms should exceed the timeout");
+    }
+
+    #[test]
+    fn test_next_interval_bad_multiplier() {
+        let mut opts = PollOptions::default();
+        let second = Duration::from_secs(1);
+        assert_eq!(next_interval(second, &opts), Duration::from_millis(1500));
+        for multiplier in [-1.0, f64::NAN, f64::INFINITY, f64::MAX] {
+            opts.multiplier = multiplier;
+            assert_eq!(next_interval(second, &opts), opts.max_interval);
+        }
+        opts.multiplier = 1e300;
+        assert_eq!(next_interval(Duration::MAX, &opts), opts.max_interval);
+    }
+}
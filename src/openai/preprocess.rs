@@ -0,0 +1,215 @@
+use image::{
+    codecs::png::{CompressionType, FilterType, PngEncoder},
+    imageops::{self, FilterType as ResizeFilter},
+    ExtendedColorType, ImageEncoder, RgbaImage,
+};
+use std::error::Error;
+
+use super::images::{
+    CreateImgVariationsOptions, EditImgOptions, ImgSize, ImgType, InvalidImg, MAX_IMG_UPLOAD_SIZE,
+};
+
+/// The smallest side an image is shrunk to while trying to fit it under `max_bytes`
+const MIN_SIDE: u32 = 64;
+
+/// How a non-square image is made square
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SquareMode {
+    /// Centre the image on a transparent square. The edits endpoint treats the padding as an area
+    /// to fill in.
+    Pad,
+    /// Cut the centre square out of the image
+    Crop,
+}
+
+#[derive(Debug, Clone)]
+pub struct PrepareImgOptions {
+    /// Larger images are shrunk to the smaller side of this size. Smaller images and `Auto` are
+    /// left as they are.
+    pub size: ImgSize,
+    pub square: SquareMode,
+    /// The image is shrunk further until its PNG fits
+    pub max_bytes: usize,
+}
+
+impl PrepareImgOptions {
+    pub fn default(size: ImgSize) -> Self {
+        Self {
+            size,
+            square: SquareMode::Pad,
+            max_bytes: MAX_IMG_UPLOAD_SIZE,
+        }
+    }
+}
+
+/// Converts a PNG, JPEG or WebP image into a square RGBA PNG the edits and variations endpoints
+/// accept.
+pub fn prepare_img(
+    img: &[u8],
+    opts: &PrepareImgOptions,
+) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let img = image::load_from_memory(img)?.to_rgba8();
+    let (width, height) = img.dimensions();
+    let side = width.min(height);
+
+    let mut img = match opts.square {
+        _ if width == height => img,
+        SquareMode::Crop => {
+            imageops::crop_imm(&img, (width - side) / 2, (height - side) / 2, side, side).to_image()
+        }
+        SquareMode::Pad => {
+            let side = width.max(height);
+            let mut square = RgbaImage::new(side, side);
+            let x = (side - width) / 2;
+            let y = (side - height) / 2;
+            imageops::overlay(&mut square, &img, x.into(), y.into());
+            square
+        }
+    };
+
+    if let Some((target_width, target_height)) = opts.size.dimensions() {
+        let target = target_width.min(target_height);
+        if img.width() > target {
+            img = imageops::resize(&img, target, target, ResizeFilter::Lanczos3);
+        }
+    }
+
+    loop {
+        let png = encode_png(&img)?;
+        if png.len() <= opts.max_bytes {
+            return Ok(png);
+        }
+        let side = img.width() * 3 / 4;
+        if side < MIN_SIDE {
+            return Err(Box::new(InvalidImg::TooLarge {
+                part: "image",
+                size: png.len(),
+            }));
+        }
+        img = imageops::resize(&img, side, side, ResizeFilter::Lanczos3);
+    }
+}
+
+pub(crate) fn encode_png(img: &RgbaImage) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let mut png = vec![];
+    PngEncoder::new_with_quality(&mut png, CompressionType::Best, FilterType::Adaptive)
+        .write_image(img, img.width(), img.height(), ExtendedColorType::Rgba8)?;
+    Ok(png)
+}
+
+/// Replaces the extension with `.png`, to match the converted image.
+fn png_file_name(file_name: &str) -> String {
+    let stem = file_name
+        .rsplit_once('.')
+        .map_or(file_name, |(stem, _)| stem);
+    format!("{stem}.png")
+}
+
+impl EditImgOptions {
+    /// Like `default`, but runs `img` through [`prepare_img`] first and asks for images of the
+    /// prepared size, unless that is `Auto`. The image is padded with transparency by default, so
+    /// without a mask the padding is what gets edited.
+    pub fn from_img(
+        file_name: &str,
+        img: &[u8],
+        prompt: &str,
+        prepare: &PrepareImgOptions,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let png = prepare_img(img, prepare)?;
+        let mut opts = Self::default(&png_file_name(file_name), png.into(), ImgType::Png, prompt);
+        if prepare.size.dimensions().is_some() {
+            opts.size = Some(prepare.size.clone());
+        }
+        Ok(opts)
+    }
+}
+
+impl CreateImgVariationsOptions {
+    /// Like `default`, but runs `img` through [`prepare_img`] first and asks for images of the
+    /// prepared size, unless that is `Auto`.
+    pub fn from_img(
+        file_name: &str,
+        img: &[u8],
+        prepare: &PrepareImgOptions,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let png = prepare_img(img, prepare)?;
+        let mut opts = Self::default(&png_file_name(file_name), png.into(), ImgType::Png);
+        if prepare.size.dimensions().is_some() {
+            opts.size = Some(prepare.size.clone());
+        }
+        Ok(opts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openai::png::read_png_info;
+    use image::{codecs::webp::WebPEncoder, Rgba};
+
+    fn webp(width: u32, height: u32) -> Vec<u8> {
+        let img = RgbaImage::from_pixel(width, height, Rgba([40, 160, 60, 255]));
+        let mut webp = vec![];
+        WebPEncoder::new_lossless(&mut webp)
+            .write_image(&img, width, height, ExtendedColorType::Rgba8)
+            .expect("error encoding WebP");
+        webp
+    }
+
+    #[test]
+    fn test_prepare_jpeg() {
+        let toad = include_bytes!("../../assets/toad.jpg");
+        let opts = EditImgOptions::from_img(
+            "toad.jpg",
+            toad,
+            "a top hat",
+            &PrepareImgOptions::default(ImgSize::Size512x512),
+        )
+        .expect("error preparing image");
+
+        assert_eq!(opts.file_name, "toad.png");
+        assert_eq!(opts.size, Some(ImgSize::Size512x512));
+        let info = read_png_info(opts.img.as_bytes().expect("not in memory")).expect("not a PNG");
+        assert_eq!((info.width, info.height, info.has_alpha), (512, 512, true));
+    }
+
+    #[test]
+    fn test_prepare_webp() {
+        // Padding centres the image on a transparent square
+        let opts = PrepareImgOptions::default(ImgSize::Size1024x1024);
+        let png = prepare_img(&webp(300, 200), &opts).expect("error preparing image");
+        let img = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(img.dimensions(), (300, 300));
+        assert_eq!(img.get_pixel(150, 10)[3], 0);
+        assert_eq!(img.get_pixel(150, 150), &Rgba([40, 160, 60, 255]));
+
+        let opts = PrepareImgOptions {
+            square: SquareMode::Crop,
+            ..PrepareImgOptions::default(ImgSize::Size256x256)
+        };
+        let variations = CreateImgVariationsOptions::from_img("toad", &webp(300, 200), &opts)
+            .expect("error preparing image");
+        assert_eq!(variations.file_name, "toad.png");
+        let info =
+            read_png_info(variations.img.as_bytes().expect("not in memory")).expect("not a PNG");
+        assert_eq!((info.width, info.height), (200, 200));
+    }
+
+    #[test]
+    fn test_prepare_shrinks_to_fit() {
+        let toad = include_bytes!("../../assets/toad.jpg");
+        let mut opts = PrepareImgOptions::default(ImgSize::Auto);
+        opts.max_bytes = 100_000;
+        let png = prepare_img(toad, &opts).expect("error preparing image");
+        assert!(png.len() <= 100_000);
+        let info = read_png_info(&png).expect("not a PNG");
+        assert!(info.width < 840 && info.width == info.height);
+
+        opts.max_bytes = 100;
+        let err = prepare_img(toad, &opts).expect_err("a 100 byte toad was produced");
+        assert!(matches!(
+            err.downcast_ref::<InvalidImg>(),
+            Some(InvalidImg::TooLarge { part: "image", .. })
+        ));
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Prices for a single model, in US dollars per 1000 tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    pub prompt: f64,
+    pub completion: f64,
+}
+
+impl ModelPricing {
+    pub fn new(prompt: f64, completion: f64) -> Self {
+        Self { prompt, completion }
+    }
+
+    pub fn cost(&self, prompt_tokens: u64, completion_tokens: u64) -> f64 {
+        (prompt_tokens as f64 * self.prompt + completion_tokens as f64 * self.completion) / 1000.0
+    }
+}
+
+/// A table of model prices. Lookups fall back to the longest known prefix of the model name, so
+/// dated snapshots like `gpt-4-0613` are priced as `gpt-4`.
+#[derive(Debug, Clone)]
+pub struct Pricing {
+    models: HashMap<String, ModelPricing>,
+    /// Fine-tuning prices in US dollars per 1000 training tokens
+    training: HashMap<String, f64>,
+}
+
+impl Default for Pricing {
+    fn default() -> Self {
+        let mut pricing = Self::empty();
+        pricing.set("gpt-4", ModelPricing::new(0.03, 0.06));
+        pricing.set("gpt-4-32k", ModelPricing::new(0.06, 0.12));
+        pricing.set("gpt-4-turbo", ModelPricing::new(0.01, 0.03));
+        pricing.set("gpt-4o", ModelPricing::new(0.0025, 0.01));
+        pricing.set("gpt-4o-mini", ModelPricing::new(0.00015, 0.0006));
+        pricing.set("gpt-3.5-turbo", ModelPricing::new(0.0015, 0.002));
+        pricing.set("gpt-3.5-turbo-16k", ModelPricing::new(0.003, 0.004));
+        pricing.set("text-davinci-003", ModelPricing::new(0.02, 0.02));
+        pricing.set("text-embedding-ada-002", ModelPricing::new(0.0001, 0.0));
+        pricing.set("text-embedding-3-small", ModelPricing::new(0.00002, 0.0));
+        pricing.set("text-embedding-3-large", ModelPricing::new(0.00013, 0.0));
+
+        pricing.set_training("gpt-3.5-turbo", 0.008);
+        pricing.set_training("gpt-4o", 0.025);
+        pricing.set_training("gpt-4o-mini", 0.003);
+        pricing.set_training("davinci-002", 0.006);
+        pricing.set_training("babbage-002", 0.0004);
+        pricing
+    }
+}
+
+impl Pricing {
+    /// A pricing table with no models in it. Every request is priced at zero until models are
+    /// added with [`Pricing::set`].
+    pub fn empty() -> Self {
+        Self {
+            models: HashMap::new(),
+            training: HashMap::new(),
+        }
+    }
+
+    pub fn set(&mut self, model: &str, pricing: ModelPricing) {
+        self.models.insert(model.to_owned(), pricing);
+    }
+
+    pub fn get(&self, model: &str) -> Option<ModelPricing> {
+        find_by_model(&self.models, model).map(|(_, pricing)| *pricing)
+    }
+
+    /// The cost in US dollars of a request, or zero if the model is not in the table.
+    pub fn cost(&self, model: &str, prompt_tokens: u64, completion_tokens: u64) -> f64 {
+        self.get(model)
+            .map(|pricing| pricing.cost(prompt_tokens, completion_tokens))
+            .unwrap_or(0.0)
+    }
+
+    /// Sets the fine-tuning price of `model` in US dollars per 1000 training tokens.
+    pub fn set_training(&mut self, model: &str, price: f64) {
+        self.training.insert(model.to_owned(), price);
+    }
+
+    /// The cost in US dollars of fine-tuning `model` on `tokens` training tokens, or `None` if the
+    /// model has no training price.
+    pub fn training_cost(&self, model: &str, tokens: u64) -> Option<f64> {
+        find_by_model(&self.training, model).map(|(_, price)| tokens as f64 * price / 1000.0)
+    }
+}
+
+/// Looks `model` up in a table keyed by model name, falling back to the longest key that is a
+/// prefix of `model`.
+pub(crate) fn find_by_model<'a, T>(
+    table: &'a HashMap<String, T>,
+    model: &str,
+) -> Option<(&'a String, &'a T)> {
+    if let Some(entry) = table.get_key_value(model) {
+        return Some(entry);
+    }
+    table
+        .iter()
+        .filter(|(name, _)| model.starts_with(name.as_str()))
+        .max_by_key(|(name, _)| name.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_longest_prefix_lookup() {
+        let pricing = Pricing::default();
+        assert_eq!(pricing.get("gpt-4-0613"), pricing.get("gpt-4"));
+        assert_eq!(pricing.get("gpt-4-32k-0613"), pricing.get("gpt-4-32k"));
+        assert_eq!(
+            pricing.get("gpt-4o-mini-2024-07-18"),
+            pricing.get("gpt-4o-mini")
+        );
+        assert_eq!(pricing.get("babbage-002"), None);
+    }
+
+    #[test]
+    fn test_cost() {
+        let mut pricing = Pricing::empty();
+        pricing.set("my-model", ModelPricing::new(1.0, 2.0));
+        assert_eq!(pricing.cost("my-model", 500, 250), 1.0);
+        assert_eq!(pricing.cost("unknown", 500, 250), 0.0);
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use reqwest::header::HeaderMap;
+
+use super::metadata::header_u64;
+use super::pricing::find_by_model;
+
+/// Limits OpenAI enforces for a model, as listed on the account's rate limits page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimits {
+    pub requests_per_minute: u64,
+    pub tokens_per_minute: u64,
+}
+
+impl RateLimits {
+    pub fn new(requests_per_minute: u64, tokens_per_minute: u64) -> Self {
+        Self {
+            requests_per_minute,
+            tokens_per_minute,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    available: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(per_minute: u64, now: Instant) -> Self {
+        Self {
+            capacity: per_minute as f64,
+            available: per_minute as f64,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.available = (self.available + elapsed * self.capacity / 60.0).min(self.capacity);
+        self.updated = now;
+    }
+
+    /// How long until `amount` is available. Requests larger than the whole bucket only wait
+    /// for it to be full, otherwise they would never be sent.
+    fn wait_for(&self, amount: f64) -> Duration {
+        let missing = amount.min(self.capacity) - self.available;
+        if missing <= 0.0 || self.capacity <= 0.0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(missing * 60.0 / self.capacity)
+    }
+
+    fn take(&mut self, amount: f64) {
+        self.available -= amount.min(self.capacity);
+    }
+
+    fn observe(&mut self, limit: Option<u64>, remaining: Option<u64>) {
+        if let Some(limit) = limit {
+            self.capacity = limit as f64;
+            self.available = self.available.min(self.capacity);
+        }
+        if let Some(remaining) = remaining {
+            self.available = self.available.min(remaining as f64);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ModelBuckets {
+    requests: Bucket,
+    tokens: Bucket,
+}
+
+/// A client-side token bucket limiter. Attach one to [`crate::OpenAIClient::rate_limiter`] and
+/// chat, completion and embedding requests wait for capacity before they are sent instead of
+/// failing with a 429. Buckets are corrected from the `x-ratelimit-*` headers of every response.
+///
+/// Limits are looked up by model name, falling back to the longest configured prefix, so limits
+/// set for `gpt-4` also cover `gpt-4-0613`. Models with no configured limits are not throttled.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    limits: HashMap<String, RateLimits>,
+    buckets: Mutex<HashMap<String, ModelBuckets>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, model: &str, limits: RateLimits) {
+        self.limits.insert(model.to_owned(), limits);
+        self.buckets
+            .get_mut()
+            .expect("rate limiter lock poisoned")
+            .remove(model);
+    }
+
+    /// Waits until a request to `model` using `tokens` tokens fits within its limits, then
+    /// reserves that capacity.
+    pub async fn acquire(&self, model: &str, tokens: u64) {
+        let Some((key, limits)) = find_by_model(&self.limits, model) else {
+            return;
+        };
+
+        loop {
+            let wait = {
+                let now = Instant::now();
+                let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+                let buckets = buckets.entry(key.clone()).or_insert_with(|| ModelBuckets {
+                    requests: Bucket::new(limits.requests_per_minute, now),
+                    tokens: Bucket::new(limits.tokens_per_minute, now),
+                });
+                buckets.requests.refill(now);
+                buckets.tokens.refill(now);
+
+                let wait = buckets
+                    .requests
+                    .wait_for(1.0)
+                    .max(buckets.tokens.wait_for(tokens as f64));
+                if wait.is_zero() {
+                    buckets.requests.take(1.0);
+                    buckets.tokens.take(tokens as f64);
+                    return;
+                }
+                wait
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Corrects the buckets for `model` from a response's `x-ratelimit-limit-*` and
+    /// `x-ratelimit-remaining-*` headers.
+    pub fn update_from_headers(&self, model: &str, headers: &HeaderMap) {
+        let Some((key, _)) = find_by_model(&self.limits, model) else {
+            return;
+        };
+
+        let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+        if let Some(buckets) = buckets.get_mut(key) {
+            let now = Instant::now();
+            buckets.requests.refill(now);
+            buckets.tokens.refill(now);
+            buckets.requests.observe(
+                header_u64(headers, "x-ratelimit-limit-requests"),
+                header_u64(headers, "x-ratelimit-remaining-requests"),
+            );
+            buckets.tokens.observe(
+                header_u64(headers, "x-ratelimit-limit-tokens"),
+                header_u64(headers, "x-ratelimit-remaining-tokens"),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use reqwest::header::HeaderValue;
+
+    #[tokio::test]
+    async fn test_acquire_waits_for_tokens() {
+        let mut limiter = RateLimiter::new();
+        limiter.set("gpt-3.5-turbo", RateLimits::new(1000, 6000));
+
+        let start = Instant::now();
+        limiter.acquire("gpt-3.5-turbo-0613", 6000).await;
+        assert!(start.elapsed() < Duration::from_millis(50));
+
+        // 6000 tokens per minute refills at 100 tokens per second
+        limiter.acquire("gpt-3.5-turbo", 10).await;
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[tokio::test]
+    async fn test_unlimited_model() {
+        let limiter = RateLimiter::new();
+        let start = Instant::now();
+        for _ in 0..100 {
+            limiter.acquire("gpt-4", 100_000).await;
+        }
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_update_from_headers() {
+        let mut limiter = RateLimiter::new();
+        limiter.set("text-embedding-ada-002", RateLimits::new(600, 1_000_000));
+        limiter.acquire("text-embedding-ada-002", 1).await;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining-requests", HeaderValue::from(0));
+        limiter.update_from_headers("text-embedding-ada-002", &headers);
+
+        // 600 requests per minute refills one request every 100ms
+        let start = Instant::now();
+        limiter.acquire("text-embedding-ada-002", 1).await;
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+}
@@ -0,0 +1,437 @@
+use std::error::Error;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::OpenAIClient;
+
+use super::responses::ResponseTool;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Encodes 16-bit little-endian PCM samples as base64, the format of `pcm16` audio frames.
+pub fn encode_pcm16(samples: &[i16]) -> String {
+    let bytes: Vec<u8> = samples
+        .iter()
+        .flat_map(|sample| sample.to_le_bytes())
+        .collect();
+    STANDARD.encode(bytes)
+}
+
+/// Decodes a base64 `pcm16` audio frame into samples. A trailing odd byte is dropped.
+pub fn decode_pcm16(audio: &str) -> Result<Vec<i16>, base64::DecodeError> {
+    let bytes = STANDARD.decode(audio)?;
+    Ok(bytes
+        .chunks_exact(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+        .collect())
+}
+
+/// Fields left as `None` keep their current value.
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RealtimeSessionConfig {
+    /// `["text"]` or `["audio", "text"]`
+    pub modalities: Option<Vec<String>>,
+    pub instructions: Option<String>,
+    pub voice: Option<String>,
+    /// `pcm16`, `g711_ulaw` or `g711_alaw`
+    pub input_audio_format: Option<String>,
+    pub output_audio_format: Option<String>,
+    pub input_audio_transcription: Option<Value>,
+    /// Server-side voice activity detection settings, or `null` to turn it off
+    pub turn_detection: Option<Value>,
+    /// Only [`ResponseTool::Function`] is supported
+    pub tools: Option<Vec<ResponseTool>>,
+    pub tool_choice: Option<Value>,
+    pub temperature: Option<f32>,
+    /// A number of tokens or `"inf"`
+    pub max_response_output_tokens: Option<Value>,
+}
+
+/// Overrides the session configuration for one response.
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RealtimeResponseConfig {
+    pub modalities: Option<Vec<String>>,
+    pub instructions: Option<String>,
+    pub voice: Option<String>,
+    pub output_audio_format: Option<String>,
+    pub tools: Option<Vec<ResponseTool>>,
+    pub tool_choice: Option<Value>,
+    pub temperature: Option<f32>,
+    pub max_output_tokens: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum RealtimeClientEvent {
+    #[serde(rename = "session.update")]
+    SessionUpdate { session: RealtimeSessionConfig },
+    /// Base64 audio in the session's input format; see [`encode_pcm16`].
+    #[serde(rename = "input_audio_buffer.append")]
+    InputAudioBufferAppend { audio: String },
+    #[serde(rename = "input_audio_buffer.commit")]
+    InputAudioBufferCommit,
+    #[serde(rename = "input_audio_buffer.clear")]
+    InputAudioBufferClear,
+    #[serde(rename = "conversation.item.create")]
+    ConversationItemCreate { item: Value },
+    #[serde(rename = "response.create")]
+    ResponseCreate {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        response: Option<RealtimeResponseConfig>,
+    },
+    #[serde(rename = "response.cancel")]
+    ResponseCancel,
+}
+
+impl RealtimeClientEvent {
+    /// Appends PCM16 samples to the input audio buffer.
+    pub fn append_audio(samples: &[i16]) -> Self {
+        RealtimeClientEvent::InputAudioBufferAppend {
+            audio: encode_pcm16(samples),
+        }
+    }
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RealtimeError {
+    #[serde(rename = "type")]
+    pub error_type: String,
+    pub code: Option<String>,
+    pub message: String,
+    pub param: Option<String>,
+    /// The client event that caused the error
+    pub event_id: Option<String>,
+}
+
+/// Events sent by the server. Event types this crate doesn't model are `Other`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum RealtimeServerEvent {
+    #[serde(rename = "error")]
+    Error { error: RealtimeError },
+    #[serde(rename = "session.created")]
+    SessionCreated { session: Value },
+    #[serde(rename = "session.updated")]
+    SessionUpdated { session: Value },
+    #[serde(rename = "input_audio_buffer.committed")]
+    InputAudioBufferCommitted { item_id: String },
+    #[serde(rename = "input_audio_buffer.speech_started")]
+    SpeechStarted {
+        audio_start_ms: u64,
+        item_id: String,
+    },
+    #[serde(rename = "input_audio_buffer.speech_stopped")]
+    SpeechStopped { audio_end_ms: u64, item_id: String },
+    #[serde(rename = "conversation.item.created")]
+    ConversationItemCreated { item: Value },
+    #[serde(rename = "response.created")]
+    ResponseCreated { response: Value },
+    /// Base64 audio in the session's output format; see [`decode_pcm16`].
+    #[serde(rename = "response.audio.delta")]
+    AudioDelta {
+        response_id: String,
+        item_id: String,
+        delta: String,
+    },
+    #[serde(rename = "response.audio.done")]
+    AudioDone {
+        response_id: String,
+        item_id: String,
+    },
+    #[serde(rename = "response.audio_transcript.delta")]
+    AudioTranscriptDelta {
+        response_id: String,
+        item_id: String,
+        delta: String,
+    },
+    #[serde(rename = "response.text.delta")]
+    TextDelta {
+        response_id: String,
+        item_id: String,
+        delta: String,
+    },
+    #[serde(rename = "response.function_call_arguments.done")]
+    FunctionCallArgumentsDone {
+        response_id: String,
+        item_id: String,
+        call_id: String,
+        name: String,
+        arguments: String,
+    },
+    #[serde(rename = "response.done")]
+    ResponseDone { response: Value },
+    #[serde(rename = "rate_limits.updated")]
+    RateLimitsUpdated { rate_limits: Vec<Value> },
+    #[serde(other)]
+    Other,
+}
+
+/// The sending half of a [`RealtimeSession`].
+pub struct RealtimeSender {
+    sink: SplitSink<Socket, Message>,
+}
+
+impl RealtimeSender {
+    pub async fn send(
+        &mut self,
+        event: &RealtimeClientEvent,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let text = serde_json::to_string(event)?;
+        self.sink.send(Message::Text(text)).await?;
+        Ok(())
+    }
+
+    pub async fn close(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.sink.close().await?;
+        Ok(())
+    }
+}
+
+/// The receiving half of a [`RealtimeSession`].
+pub struct RealtimeReceiver {
+    stream: SplitStream<Socket>,
+}
+
+impl RealtimeReceiver {
+    /// The next event from the server, or `None` once the connection has closed.
+    pub async fn next_event(
+        &mut self,
+    ) -> Option<Result<RealtimeServerEvent, Box<dyn Error + Send + Sync>>> {
+        loop {
+            let message = match self.stream.next().await? {
+                Ok(message) => message,
+                Err(err) => return Some(Err(err.into())),
+            };
+            match message {
+                Message::Text(text) => {
+                    return Some(serde_json::from_str(&text).map_err(|err| err.into()));
+                }
+                Message::Close(_) => return None,
+                _ => continue,
+            }
+        }
+    }
+}
+
+/// A Realtime API conversation over a WebSocket. Use [`RealtimeSession::split`] to send audio
+/// while waiting for events on another task.
+pub struct RealtimeSession {
+    sender: RealtimeSender,
+    receiver: RealtimeReceiver,
+}
+
+impl RealtimeSession {
+    pub async fn send(
+        &mut self,
+        event: &RealtimeClientEvent,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.sender.send(event).await
+    }
+
+    pub async fn next_event(
+        &mut self,
+    ) -> Option<Result<RealtimeServerEvent, Box<dyn Error + Send + Sync>>> {
+        self.receiver.next_event().await
+    }
+
+    pub async fn close(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.sender.close().await
+    }
+
+    pub fn split(self) -> (RealtimeSender, RealtimeReceiver) {
+        (self.sender, self.receiver)
+    }
+}
+
+/// The WebSocket URL of a session with `model`, which is percent-encoded into the query.
+fn realtime_url(base_uri: &str, model: &str) -> Result<Url, Box<dyn Error + Send + Sync>> {
+    let base_uri = match base_uri.split_once("://") {
+        Some(("https", rest)) => format!("wss://{rest}"),
+        Some(("http", rest)) => format!("ws://{rest}"),
+        _ => base_uri.to_owned(),
+    };
+    let mut url = Url::parse(&format!("{base_uri}/realtime"))?;
+    url.query_pairs_mut().append_pair("model", model);
+    Ok(url)
+}
+
+impl OpenAIClient {
+    /// Opens a Realtime session with `model`. The WebSocket URL is derived from `base_uri`.
+    pub async fn connect_realtime(
+        &self,
+        model: &str,
+    ) -> Result<RealtimeSession, Box<dyn Error + Send + Sync>> {
+        let mut request = realtime_url(&self.base_uri, model)?
+            .as_str()
+            .into_client_request()?;
+        let headers = request.headers_mut();
+        headers.insert("Authorization", format!("Bearer {}", self.api_key).parse()?);
+        headers.insert("OpenAI-Beta", "realtime=v1".parse()?);
+
+        let (socket, _) = tokio_tungstenite::connect_async(request).await?;
+        let (sink, stream) = socket.split();
+        Ok(RealtimeSession {
+            sender: RealtimeSender { sink },
+            receiver: RealtimeReceiver { stream },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+
+    #[test]
+    fn test_pcm16_round_trip() {
+        let samples = [0, 1, -1, i16::MAX, i16::MIN];
+        let audio = encode_pcm16(&samples);
+        assert_eq!(audio, "AAABAP///38AgA==");
+        assert_eq!(decode_pcm16(&audio).expect("invalid audio"), samples);
+    }
+
+    #[test]
+    fn test_realtime_url_encodes_model() {
+        let url = realtime_url("https://api.openai.com/v1", "gpt-4o-realtime-preview")
+            .expect("invalid URL");
+        assert_eq!(
+            url.as_str(),
+            "wss://api.openai.com/v1/realtime?model=gpt-4o-realtime-preview"
+        );
+
+        let url = realtime_url("http://localhost:8080/v1", "ft:toad&pond=north #1?")
+            .expect("invalid URL");
+        assert_eq!(
+            url.as_str(),
+            "ws://localhost:8080/v1/realtime?model=ft%3Atoad%26pond%3Dnorth+%231%3F"
+        );
+        let pairs: Vec<_> = url.query_pairs().collect();
+        assert_eq!(pairs, [("model".into(), "ft:toad&pond=north #1?".into())]);
+    }
+
+    // The error type is fixed by tungstenite's handshake callback
+    #[allow(clippy::result_large_err)]
+    fn check_handshake(request: &Request, response: Response) -> Result<Response, ErrorResponse> {
+        assert_eq!(request.uri(), "/v1/realtime?model=gpt-4o-realtime-preview");
+        assert_eq!(request.headers()["Authorization"], "Bearer test-key");
+        assert_eq!(request.headers()["OpenAI-Beta"], "realtime=v1");
+        Ok(response)
+    }
+
+    /// Accepts one connection, checks the handshake, then answers the client's first two events
+    /// with a scripted sequence.
+    async fn stand_in(listener: TcpListener) -> Vec<Value> {
+        let (stream, _) = listener.accept().await.expect("error accepting");
+        let mut socket = tokio_tungstenite::accept_hdr_async(stream, check_handshake)
+            .await
+            .expect("error accepting websocket");
+
+        let script = [
+            json!({ "type": "session.created", "event_id": "ev-1", "session": { "id": "sess-1" } }),
+            json!({ "type": "response.audio.delta", "event_id": "ev-2", "response_id": "resp-1",
+                    "item_id": "item-1", "output_index": 0, "content_index": 0,
+                    "delta": encode_pcm16(&[100, -100]) }),
+            json!({ "type": "response.audio_transcript.delta", "event_id": "ev-3",
+                    "response_id": "resp-1", "item_id": "item-1", "delta": "Ribbit" }),
+            json!({ "type": "response.output_item.done", "event_id": "ev-4" }),
+            json!({ "type": "response.done", "event_id": "ev-5", "response": { "id": "resp-1" } }),
+        ];
+
+        let mut received = vec![];
+        while received.len() < 2 {
+            if let Some(Ok(Message::Text(text))) = socket.next().await {
+                received.push(serde_json::from_str(&text).expect("invalid event"));
+            }
+        }
+        for event in script {
+            socket
+                .send(Message::Text(event.to_string()))
+                .await
+                .expect("error sending");
+        }
+        socket.close(None).await.expect("error closing");
+        received
+    }
+
+    #[tokio::test]
+    pub async fn test_realtime_session() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("error binding");
+        let addr = listener.local_addr().expect("no address");
+        let server = tokio::spawn(stand_in(listener));
+
+        let client = OpenAIClient::new("test-key", &format!("http://{addr}/v1"));
+        let mut session = client
+            .connect_realtime("gpt-4o-realtime-preview")
+            .await
+            .expect("error connecting");
+        session
+            .send(&RealtimeClientEvent::SessionUpdate {
+                session: RealtimeSessionConfig {
+                    modalities: Some(vec!["audio".to_owned(), "text".to_owned()]),
+                    voice: Some("alloy".to_owned()),
+                    ..Default::default()
+                },
+            })
+            .await
+            .expect("error sending");
+        session
+            .send(&RealtimeClientEvent::append_audio(&[1, 2, 3]))
+            .await
+            .expect("error sending");
+
+        let mut events = vec![];
+        while let Some(event) = session.next_event().await {
+            events.push(event.expect("error receiving"));
+        }
+
+        assert!(matches!(
+            events[0],
+            RealtimeServerEvent::SessionCreated { .. }
+        ));
+        match &events[1] {
+            RealtimeServerEvent::AudioDelta { delta, .. } => {
+                assert_eq!(decode_pcm16(delta).expect("invalid audio"), [100, -100])
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+        assert!(
+            matches!(&events[2], RealtimeServerEvent::AudioTranscriptDelta { delta, .. } if delta == "Ribbit")
+        );
+        assert!(matches!(events[3], RealtimeServerEvent::Other));
+        assert!(matches!(
+            events[4],
+            RealtimeServerEvent::ResponseDone { .. }
+        ));
+        assert_eq!(events.len(), 5);
+
+        let received = server.await.expect("stand-in panicked");
+        assert_eq!(
+            received[0],
+            json!({
+                "type": "session.update",
+                "session": { "modalities": ["audio", "text"], "voice": "alloy" }
+            })
+        );
+        assert_eq!(
+            received[1],
+            json!({ "type": "input_audio_buffer.append", "audio": "AQACAAMA" })
+        );
+    }
+}
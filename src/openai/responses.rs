@@ -0,0 +1,784 @@
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::pin::Pin;
+
+use bytes::Bytes;
+use futures_util::{stream, Stream, StreamExt};
+use reqwest::Method;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::OpenAIClient;
+
+use super::chat::{ChatFunction, ChatRole};
+use super::client::ReservedBudget;
+use super::deletion::DeletionStatus;
+use super::metadata::ApiResponse;
+use super::sse::SseParser;
+use super::tokens::estimate_tokens;
+use super::usage::Usage;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum InputContent {
+    #[serde(rename = "input_text")]
+    Text { text: String },
+    /// Either `image_url` (a web or `data:` URL) or `file_id` must be set.
+    #[serde(rename = "input_image")]
+    Image {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        image_url: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        file_id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        detail: Option<String>,
+    },
+}
+
+impl InputContent {
+    pub fn text(text: &str) -> Self {
+        InputContent::Text {
+            text: text.to_owned(),
+        }
+    }
+
+    pub fn image_url(url: &str) -> Self {
+        InputContent::Image {
+            image_url: Some(url.to_owned()),
+            file_id: None,
+            detail: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ResponseInputItem {
+    #[serde(rename = "message")]
+    Message {
+        role: ChatRole,
+        content: Vec<InputContent>,
+    },
+    /// The result of a function call the model made in a previous response.
+    #[serde(rename = "function_call_output")]
+    FunctionCallOutput { call_id: String, output: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ResponseInput {
+    Text(String),
+    Items(Vec<ResponseInputItem>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ResponseTool {
+    #[serde(rename = "function")]
+    Function {
+        #[serde(flatten)]
+        function: ChatFunction,
+    },
+    #[serde(rename = "file_search")]
+    FileSearch { vector_store_ids: Vec<String> },
+    #[serde(rename = "web_search_preview")]
+    WebSearchPreview,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ResponseFormat {
+    #[serde(rename = "text")]
+    Text,
+    #[serde(rename = "json_object")]
+    JsonObject,
+    /// Constrains the output to `schema`. Parse it with [`Response::parse_output`].
+    #[serde(rename = "json_schema")]
+    JsonSchema {
+        name: String,
+        schema: Value,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        strict: Option<bool>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseTextConfig {
+    pub format: ResponseFormat,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateResponseOptions {
+    pub model: String,
+    pub input: ResponseInput,
+    pub instructions: Option<String>,
+    pub tools: Option<Vec<ResponseTool>>,
+    pub tool_choice: Option<Value>,
+    /// Continues the conversation from an earlier stored response
+    pub previous_response_id: Option<String>,
+    pub text: Option<ResponseTextConfig>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub max_output_tokens: Option<u64>,
+    pub store: Option<bool>,
+    /// Runs the response asynchronously. Poll it with `get_response`, or stop it with
+    /// `cancel_response`.
+    pub background: Option<bool>,
+    pub metadata: Option<HashMap<String, String>>,
+    pub user: Option<String>,
+}
+
+impl CreateResponseOptions {
+    pub fn default(model: &str, input: ResponseInput) -> Self {
+        Self {
+            model: model.to_owned(),
+            input,
+            instructions: None,
+            tools: None,
+            tool_choice: None,
+            previous_response_id: None,
+            text: None,
+            temperature: None,
+            top_p: None,
+            max_output_tokens: None,
+            store: None,
+            background: None,
+            metadata: None,
+            user: None,
+        }
+    }
+
+    /// An estimate of the tokens in the instructions, input and tool definitions. Images aren't
+    /// counted.
+    pub fn estimated_input_tokens(&self) -> u64 {
+        let input = match &self.input {
+            ResponseInput::Text(text) => estimate_tokens(text),
+            ResponseInput::Items(items) => items
+                .iter()
+                .map(|item| match item {
+                    ResponseInputItem::Message { content, .. } => content
+                        .iter()
+                        .map(|part| match part {
+                            InputContent::Text { text } => estimate_tokens(text),
+                            InputContent::Image { .. } => 0,
+                        })
+                        .sum(),
+                    ResponseInputItem::FunctionCallOutput { output, .. } => estimate_tokens(output),
+                })
+                .sum(),
+        };
+        let instructions = self.instructions.as_deref().map_or(0, estimate_tokens);
+        let tools = self
+            .tools
+            .as_ref()
+            .and_then(|tools| serde_json::to_string(tools).ok())
+            .map_or(0, |tools| estimate_tokens(&tools));
+        input + instructions + tools
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ResponseStatus {
+    #[serde(rename = "queued")]
+    Queued,
+    #[serde(rename = "in_progress")]
+    InProgress,
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "failed")]
+    Failed,
+    #[serde(rename = "cancelled")]
+    Cancelled,
+    #[serde(rename = "incomplete")]
+    Incomplete,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum OutputContent {
+    #[serde(rename = "output_text")]
+    Text {
+        text: String,
+        #[serde(default)]
+        annotations: Vec<Value>,
+    },
+    #[serde(rename = "refusal")]
+    Refusal { refusal: String },
+    #[serde(other)]
+    Other,
+}
+
+/// Item types this crate doesn't model yet, such as tool call results, are `Other`; use
+/// [`OpenAIClient::create_response_with_metadata`] to read them from the raw response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ResponseOutputItem {
+    #[serde(rename = "message")]
+    Message {
+        id: String,
+        role: ChatRole,
+        content: Vec<OutputContent>,
+    },
+    #[serde(rename = "function_call")]
+    FunctionCall {
+        id: Option<String>,
+        call_id: String,
+        name: String,
+        /// JSON-encoded arguments, as generated by the model
+        arguments: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub total_tokens: u64,
+}
+
+impl From<&ResponseUsage> for Usage {
+    fn from(usage: &ResponseUsage) -> Self {
+        Usage {
+            prompt_tokens: usage.input_tokens,
+            completion_tokens: Some(usage.output_tokens),
+            total_tokens: usage.total_tokens,
+        }
+    }
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Response {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub status: ResponseStatus,
+    pub model: String,
+    #[serde(default)]
+    pub output: Vec<ResponseOutputItem>,
+    pub usage: Option<ResponseUsage>,
+    pub error: Option<Value>,
+    pub incomplete_details: Option<Value>,
+    pub previous_response_id: Option<String>,
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+impl Response {
+    /// The text of every output message, concatenated.
+    pub fn output_text(&self) -> String {
+        let mut text = String::new();
+        for item in &self.output {
+            if let ResponseOutputItem::Message { content, .. } = item {
+                for part in content {
+                    if let OutputContent::Text { text: part, .. } = part {
+                        text += part;
+                    }
+                }
+            }
+        }
+        text
+    }
+
+    /// Parses structured output requested with [`ResponseFormat::JsonSchema`].
+    pub fn parse_output<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_str(&self.output_text())
+    }
+}
+
+/// The semantic events streamed while a response is generated. Event types this crate doesn't
+/// model are `Other`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ResponseStreamEvent {
+    #[serde(rename = "response.created")]
+    Created { response: Response },
+    #[serde(rename = "response.in_progress")]
+    InProgress { response: Response },
+    #[serde(rename = "response.completed")]
+    Completed { response: Response },
+    #[serde(rename = "response.failed")]
+    Failed { response: Response },
+    #[serde(rename = "response.incomplete")]
+    Incomplete { response: Response },
+    #[serde(rename = "response.output_item.added")]
+    OutputItemAdded {
+        output_index: u32,
+        item: ResponseOutputItem,
+    },
+    #[serde(rename = "response.output_item.done")]
+    OutputItemDone {
+        output_index: u32,
+        item: ResponseOutputItem,
+    },
+    #[serde(rename = "response.output_text.delta")]
+    OutputTextDelta {
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        delta: String,
+    },
+    #[serde(rename = "response.output_text.done")]
+    OutputTextDone {
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        text: String,
+    },
+    #[serde(rename = "response.refusal.delta")]
+    RefusalDelta { item_id: String, delta: String },
+    #[serde(rename = "response.function_call_arguments.delta")]
+    FunctionCallArgumentsDelta {
+        item_id: String,
+        output_index: u32,
+        delta: String,
+    },
+    #[serde(rename = "response.function_call_arguments.done")]
+    FunctionCallArgumentsDone {
+        item_id: String,
+        output_index: u32,
+        arguments: String,
+    },
+    #[serde(rename = "error")]
+    Error {
+        code: Option<String>,
+        message: String,
+        param: Option<String>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+struct EventStream {
+    body: Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>,
+    parser: SseParser,
+    pending: VecDeque<String>,
+}
+
+impl OpenAIClient {
+    pub async fn create_response(
+        &self,
+        opts: &CreateResponseOptions,
+    ) -> Result<Response, Box<dyn Error + Send + Sync>> {
+        Ok(self.create_response_with_metadata(opts).await?.data)
+    }
+
+    pub async fn create_response_with_metadata(
+        &self,
+        opts: &CreateResponseOptions,
+    ) -> Result<ApiResponse<Response>, Box<dyn Error + Send + Sync>> {
+        let reserved = self.reserve_response(opts).await?;
+        let res: ApiResponse<Response> = self
+            .post_json_rate_limited(&opts.model, "/responses", opts)
+            .await?;
+        if let Some(usage) = &res.data.usage {
+            self.record_reserved_usage(
+                reserved,
+                &res.data.model,
+                opts.user.as_deref(),
+                &usage.into(),
+            );
+        }
+        Ok(res)
+    }
+
+    /// Checks a response against the budgets, reserving its input and `max_output_tokens`, and
+    /// waits for the rate limiter. Without `max_output_tokens` only the input is reserved.
+    async fn reserve_response(
+        &self,
+        opts: &CreateResponseOptions,
+    ) -> Result<ReservedBudget, Box<dyn Error + Send + Sync>> {
+        let input_tokens = opts.estimated_input_tokens();
+        let max_tokens = opts.max_output_tokens.unwrap_or(0);
+        let reserved = self.check_budget(&opts.model, input_tokens, max_tokens)?;
+        self.wait_for_rate_limit(&opts.model, input_tokens + max_tokens)
+            .await;
+        Ok(reserved)
+    }
+
+    /// Creates a response and streams its events as they arrive. Usage is recorded when the
+    /// `response.completed` event is received, and the budget reservation is held until then.
+    pub async fn create_response_stream(
+        &self,
+        opts: &CreateResponseOptions,
+    ) -> Result<
+        impl Stream<Item = Result<ResponseStreamEvent, Box<dyn Error + Send + Sync>>>,
+        Box<dyn Error + Send + Sync>,
+    > {
+        let mut body = serde_json::to_value(opts)?;
+        body["stream"] = Value::Bool(true);
+        let reserved = self.reserve_response(opts).await?;
+        let request = self.request(Method::POST, "/responses").json(&body);
+        let res = self.send_rate_limited(&opts.model, request).await?;
+
+        let state = EventStream {
+            body: Box::pin(res.bytes_stream()),
+            parser: SseParser::default(),
+            pending: VecDeque::new(),
+        };
+        let client = self.clone();
+        let user = opts.user.clone();
+        let mut reserved = Some(reserved);
+        let events = stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(data) = state.pending.pop_front() {
+                    let event = serde_json::from_str::<ResponseStreamEvent>(&data)
+                        .map_err(|err| -> Box<dyn Error + Send + Sync> { Box::new(err) });
+                    return Some((event, state));
+                }
+                match state.body.next().await? {
+                    Ok(chunk) => state.pending.extend(
+                        state
+                            .parser
+                            .push(&chunk)
+                            .into_iter()
+                            .map(|event| event.data)
+                            .filter(|data| data != "[DONE]"),
+                    ),
+                    Err(err) => return Some((Err(err.into()), state)),
+                }
+            }
+        })
+        .inspect(move |event| {
+            if let Ok(ResponseStreamEvent::Completed { response }) = event {
+                if let (Some(usage), Some(reserved)) = (&response.usage, reserved.take()) {
+                    client.record_reserved_usage(
+                        reserved,
+                        &response.model,
+                        user.as_deref(),
+                        &usage.into(),
+                    );
+                }
+            }
+        });
+        Ok(events)
+    }
+
+    pub async fn get_response(
+        &self,
+        response_id: &str,
+    ) -> Result<Response, Box<dyn Error + Send + Sync>> {
+        Ok(self
+            .get_json(&format!("/responses/{response_id}"))
+            .await?
+            .data)
+    }
+
+    pub async fn delete_response(
+        &self,
+        response_id: &str,
+    ) -> Result<DeletionStatus, Box<dyn Error + Send + Sync>> {
+        Ok(self
+            .delete_json(&format!("/responses/{response_id}"))
+            .await?
+            .data)
+    }
+
+    /// Cancels a response created with `background` set. Other responses can't be cancelled.
+    pub async fn cancel_response(
+        &self,
+        response_id: &str,
+    ) -> Result<Response, Box<dyn Error + Send + Sync>> {
+        let request = self.request(Method::POST, &format!("/responses/{response_id}/cancel"));
+        Ok(self.send_json(request).await?.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::openai::budget::{Budget, BudgetExceeded, BudgetLimit, BudgetManager, BudgetScope};
+    use crate::openai::pricing::Pricing;
+    use crate::openai::usage::UsageTracker;
+    use serde_json::json;
+    use std::sync::Arc;
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn response_json(status: &str, text: &str) -> Value {
+        json!({
+            "id": "resp-1",
+            "object": "response",
+            "created_at": 1741476542,
+            "status": status,
+            "model": "gpt-4o-2024-08-06",
+            "output": [{
+                "type": "message",
+                "id": "msg-1",
+                "status": "completed",
+                "role": "assistant",
+                "content": [{ "type": "output_text", "text": text, "annotations": [] }]
+            }, {
+                "type": "web_search_call",
+                "id": "ws-1",
+                "status": "completed"
+            }],
+            "usage": if status == "completed" {
+                json!({ "input_tokens": 36, "output_tokens": 9, "total_tokens": 45 })
+            } else {
+                Value::Null
+            },
+            "previous_response_id": "resp-0"
+        })
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Pond {
+        name: String,
+        temperature: f64,
+    }
+
+    #[tokio::test]
+    pub async fn test_create_response() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/responses"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json(
+                "completed",
+                "{\"name\":\"north\",\"temperature\":18.5}",
+            )))
+            .mount(&server)
+            .await;
+
+        let mut client = OpenAIClient::new("test-key", &server.uri());
+        let tracker = Arc::new(UsageTracker::default());
+        client.usage_tracker = Some(tracker.clone());
+
+        let mut opts = CreateResponseOptions::default(
+            "gpt-4o",
+            ResponseInput::Items(vec![ResponseInputItem::Message {
+                role: ChatRole::User,
+                content: vec![
+                    InputContent::text("Which pond is this?"),
+                    InputContent::image_url("https://example.com/pond.png"),
+                ],
+            }]),
+        );
+        opts.previous_response_id = Some("resp-0".to_owned());
+        opts.tools = Some(vec![ResponseTool::Function {
+            function: ChatFunction {
+                name: "get_pond_temperature".to_owned(),
+                description: None,
+                parameters: json!({ "type": "object", "properties": {} }),
+            },
+        }]);
+        opts.text = Some(ResponseTextConfig {
+            format: ResponseFormat::JsonSchema {
+                name: "pond".to_owned(),
+                schema: json!({ "type": "object" }),
+                strict: Some(true),
+            },
+        });
+        let response = client
+            .create_response(&opts)
+            .await
+            .expect("error creating response");
+
+        let pond: Pond = response.parse_output().expect("error parsing output");
+        assert_eq!(pond.name, "north");
+        assert_eq!(pond.temperature, 18.5);
+        assert!(matches!(response.output[1], ResponseOutputItem::Other));
+        assert_eq!(tracker.snapshot().total.prompt_tokens, 36);
+
+        let requests = server.received_requests().await.expect("no requests");
+        let body: Value = serde_json::from_slice(&requests[0].body).expect("invalid body");
+        assert_eq!(
+            body,
+            json!({
+                "model": "gpt-4o",
+                "input": [{
+                    "type": "message",
+                    "role": "user",
+                    "content": [
+                        { "type": "input_text", "text": "Which pond is this?" },
+                        { "type": "input_image", "image_url": "https://example.com/pond.png" }
+                    ]
+                }],
+                "tools": [{
+                    "type": "function",
+                    "name": "get_pond_temperature",
+                    "parameters": { "type": "object", "properties": {} }
+                }],
+                "previous_response_id": "resp-0",
+                "text": {
+                    "format": {
+                        "type": "json_schema",
+                        "name": "pond",
+                        "schema": { "type": "object" },
+                        "strict": true
+                    }
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_budget_blocks_response() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/responses"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(response_json("completed", "Ribbit.")),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut client = OpenAIClient::new("test-key", &server.uri());
+        let budgets = Arc::new(BudgetManager::new(
+            vec![Budget::hard(
+                BudgetScope::All,
+                BudgetLimit::Tokens(100),
+                None,
+            )],
+            Pricing::empty(),
+        ));
+        client.budgets = Some(budgets.clone());
+
+        let mut opts =
+            CreateResponseOptions::default("gpt-4o", ResponseInput::Text("Say ribbit".to_owned()));
+        opts.max_output_tokens = Some(200);
+        let err = client
+            .create_response(&opts)
+            .await
+            .expect_err("a response over the budget was created");
+        assert_eq!(
+            err.downcast_ref::<BudgetExceeded>()
+                .expect("expected BudgetExceeded")
+                .estimated_tokens,
+            203
+        );
+        assert!(client.create_response_stream(&opts).await.is_err());
+
+        // The usage recorded replaces the reservation
+        opts.max_output_tokens = Some(50);
+        client
+            .create_response(&opts)
+            .await
+            .expect("error creating response");
+        let err = budgets
+            .check(None, "gpt-4o", 60, 0)
+            .expect_err("recorded usage should count against the budget");
+        assert_eq!((err.spent_tokens, err.reserved_tokens), (45, 0));
+    }
+
+    #[tokio::test]
+    pub async fn test_cancel_background_response() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/responses"))
+            .and(body_json(json!({
+                "model": "gpt-4o",
+                "input": "Write a long poem about toads",
+                "background": true
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json("queued", "")))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/responses/resp-1/cancel"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json("cancelled", "")))
+            .mount(&server)
+            .await;
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let mut opts = CreateResponseOptions::default(
+            "gpt-4o",
+            ResponseInput::Text("Write a long poem about toads".to_owned()),
+        );
+        opts.background = Some(true);
+        let response = client
+            .create_response(&opts)
+            .await
+            .expect("error creating response");
+        assert_eq!(response.status, ResponseStatus::Queued);
+
+        let response = client
+            .cancel_response(&response.id)
+            .await
+            .expect("error cancelling response");
+        assert_eq!(response.status, ResponseStatus::Cancelled);
+    }
+
+    #[tokio::test]
+    pub async fn test_create_response_stream() {
+        let events = [
+            json!({ "type": "response.created", "response": response_json("in_progress", "") }),
+            json!({
+                "type": "response.output_text.delta",
+                "item_id": "msg-1", "output_index": 0, "content_index": 0, "delta": "Rib"
+            }),
+            json!({
+                "type": "response.output_text.delta",
+                "item_id": "msg-1", "output_index": 0, "content_index": 0, "delta": "bit."
+            }),
+            json!({
+                "type": "response.function_call_arguments.delta",
+                "item_id": "fc-1", "output_index": 1, "delta": "{\"pond\":"
+            }),
+            json!({ "type": "response.content_part.done", "item_id": "msg-1" }),
+            json!({ "type": "response.completed", "response": response_json("completed", "Ribbit.") }),
+        ];
+        let body: String = events
+            .iter()
+            .map(|event| {
+                format!(
+                    "event: {}\ndata: {event}\n\n",
+                    event["type"].as_str().unwrap()
+                )
+            })
+            .collect();
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/responses"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string(body),
+            )
+            .mount(&server)
+            .await;
+
+        let mut client = OpenAIClient::new("test-key", &server.uri());
+        let tracker = Arc::new(UsageTracker::default());
+        client.usage_tracker = Some(tracker.clone());
+
+        let opts = CreateResponseOptions::default("gpt-4o", ResponseInput::Text("hi".to_owned()));
+        let mut stream = Box::pin(
+            client
+                .create_response_stream(&opts)
+                .await
+                .expect("error creating response"),
+        );
+        let mut text = String::new();
+        let mut arguments = String::new();
+        let mut completed = None;
+        let mut others = 0;
+        while let Some(event) = stream.next().await {
+            match event.expect("error reading event") {
+                ResponseStreamEvent::OutputTextDelta { delta, .. } => text += &delta,
+                ResponseStreamEvent::FunctionCallArgumentsDelta { delta, .. } => {
+                    arguments += &delta
+                }
+                ResponseStreamEvent::Completed { response } => completed = Some(response),
+                ResponseStreamEvent::Other => others += 1,
+                _ => {}
+            }
+        }
+
+        assert_eq!(text, "Ribbit.");
+        assert_eq!(arguments, "{\"pond\":");
+        assert_eq!(others, 1);
+        let completed = completed.expect("no completed event");
+        assert_eq!(completed.output_text(), "Ribbit.");
+        assert_eq!(tracker.snapshot().total.completion_tokens, 9);
+
+        let requests = server.received_requests().await.expect("no requests");
+        let body: Value = serde_json::from_slice(&requests[0].body).expect("invalid body");
+        assert_eq!(body["stream"], true);
+    }
+}
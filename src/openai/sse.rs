@@ -0,0 +1,82 @@
+/// One server-sent event. Multi-line `data` fields are joined with newlines.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SseEvent {
+    pub(crate) event: Option<String>,
+    pub(crate) data: String,
+}
+
+/// Splits a `text/event-stream` body into events as chunks of it arrive. Chunks may end anywhere,
+/// including in the middle of a line or a UTF-8 character.
+#[derive(Debug, Default)]
+pub(crate) struct SseParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = vec![];
+        while let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(SseEvent {
+                        event: self.event.take(),
+                        data: self.data.join("\n"),
+                    });
+                    self.data.clear();
+                }
+                self.event = None;
+                continue;
+            }
+            if line.starts_with(':') {
+                continue;
+            }
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => self.event = Some(value.to_owned()),
+                "data" => self.data.push(value.to_owned()),
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_chunks() {
+        let body = "event: greeting\r\ndata: {\"text\":\"h\u{e9}llo\"}\r\n\r\n: keep-alive\n\ndata: a\ndata: b\n\n";
+        let bytes = body.as_bytes();
+
+        // Feed one byte at a time so every line and the two-byte character are split
+        let mut parser = SseParser::default();
+        let events: Vec<SseEvent> = bytes
+            .chunks(1)
+            .flat_map(|chunk| parser.push(chunk))
+            .collect();
+
+        assert_eq!(
+            events,
+            [
+                SseEvent {
+                    event: Some("greeting".to_owned()),
+                    data: "{\"text\":\"h\u{e9}llo\"}".to_owned()
+                },
+                SseEvent {
+                    event: None,
+                    data: "a\nb".to_owned()
+                }
+            ]
+        );
+    }
+}
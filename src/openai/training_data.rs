@@ -0,0 +1,377 @@
+use core::fmt;
+use std::error::Error;
+use std::fmt::Display;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::chat::{estimate_message_tokens, ChatFunction, ChatMessage, ChatRole};
+use super::pricing::Pricing;
+
+/// One conversation in a chat-format fine-tuning file.
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrainingExample {
+    pub messages: Vec<ChatMessage>,
+    pub functions: Option<Vec<ChatFunction>>,
+}
+
+impl TrainingExample {
+    pub fn new(messages: Vec<ChatMessage>) -> Self {
+        Self {
+            messages,
+            functions: None,
+        }
+    }
+}
+
+/// Builds the JSONL file expected by [`crate::OpenAIClient::create_fine_tuning_job`].
+#[derive(Debug, Clone, Default)]
+pub struct TrainingDataset {
+    pub examples: Vec<TrainingExample>,
+}
+
+impl TrainingDataset {
+    pub fn push(&mut self, example: TrainingExample) {
+        self.examples.push(example);
+    }
+
+    pub fn to_jsonl(&self) -> serde_json::Result<String> {
+        let mut jsonl = String::new();
+        for example in &self.examples {
+            jsonl += &serde_json::to_string(example)?;
+            jsonl.push('\n');
+        }
+        Ok(jsonl)
+    }
+
+    pub async fn write_to(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        tokio::fs::write(path, self.to_jsonl()?).await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DatasetIssueKind {
+    /// The line is not JSON, or does not match the chat fine-tuning schema
+    InvalidSchema(String),
+    NoMessages,
+    NoAssistantMessage,
+    SystemMessageNotFirst,
+    FirstMessageNotUser,
+    /// Two messages in a row from the same role
+    RepeatedRole(ChatRole),
+    /// A message with no content that doesn't make a function or tool call either
+    MissingContent(ChatRole),
+    TooManyTokens {
+        tokens: u64,
+        limit: u64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatasetIssue {
+    /// 1-based line number in the JSONL file
+    pub line: usize,
+    pub kind: DatasetIssueKind,
+}
+
+impl Display for DatasetIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            DatasetIssueKind::InvalidSchema(err) => write!(f, "invalid example: {err}"),
+            DatasetIssueKind::NoMessages => write!(f, "example has no messages"),
+            DatasetIssueKind::NoAssistantMessage => write!(f, "example has no assistant message"),
+            DatasetIssueKind::SystemMessageNotFirst => {
+                write!(f, "system message is not the first message")
+            }
+            DatasetIssueKind::FirstMessageNotUser => {
+                write!(f, "first non-system message is not from the user")
+            }
+            DatasetIssueKind::RepeatedRole(role) => {
+                write!(f, "two {role:?} messages in a row")
+            }
+            DatasetIssueKind::MissingContent(role) => {
+                write!(f, "{role:?} message has no content or function call")
+            }
+            DatasetIssueKind::TooManyTokens { tokens, limit } => {
+                write!(f, "example is ~{tokens} tokens, over the limit of {limit}")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DatasetReport {
+    pub examples: usize,
+    pub issues: Vec<DatasetIssue>,
+    /// Estimated tokens trained on in one epoch. Examples over the token limit are counted at the
+    /// limit, since OpenAI truncates them.
+    pub estimated_tokens: u64,
+    /// `None` if the model has no training price
+    pub estimated_cost_per_epoch: Option<f64>,
+}
+
+impl DatasetReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// The most tokens a single training example may contain when fine-tuning `model`.
+pub fn example_token_limit(model: &str) -> Option<u64> {
+    const LIMITS: [(&str, u64); 6] = [
+        ("gpt-3.5-turbo-0613", 4096),
+        ("gpt-3.5-turbo", 16385),
+        ("gpt-4o-mini", 65536),
+        ("gpt-4o", 65536),
+        ("babbage-002", 16384),
+        ("davinci-002", 16384),
+    ];
+    LIMITS
+        .iter()
+        .filter(|(prefix, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, limit)| *limit)
+}
+
+fn check_roles(messages: &[ChatMessage]) -> Vec<DatasetIssueKind> {
+    let mut issues = vec![];
+    if messages.is_empty() {
+        issues.push(DatasetIssueKind::NoMessages);
+        return issues;
+    }
+    if !messages
+        .iter()
+        .any(|message| message.role == ChatRole::Assistant)
+    {
+        issues.push(DatasetIssueKind::NoAssistantMessage);
+    }
+    if messages
+        .iter()
+        .skip(1)
+        .any(|message| message.role == ChatRole::System)
+    {
+        issues.push(DatasetIssueKind::SystemMessageNotFirst);
+    }
+    if let Some(first) = messages
+        .iter()
+        .find(|message| message.role != ChatRole::System)
+    {
+        if first.role != ChatRole::User {
+            issues.push(DatasetIssueKind::FirstMessageNotUser);
+        }
+    }
+    if let Some(message) = messages.iter().find(|message| {
+        message.content.is_none() && message.function_call.is_none() && message.tool_calls.is_none()
+    }) {
+        issues.push(DatasetIssueKind::MissingContent(message.role));
+    }
+    for pair in messages.windows(2) {
+        let role = pair[0].role;
+        if role == pair[1].role && matches!(role, ChatRole::User | ChatRole::Assistant) {
+            issues.push(DatasetIssueKind::RepeatedRole(role));
+            break;
+        }
+    }
+    issues
+}
+
+/// Checks a chat-format fine-tuning file for problems OpenAI would reject or silently truncate,
+/// and estimates the tokens and cost of training `model` on it.
+pub fn validate_dataset(jsonl: &str, model: &str, pricing: &Pricing) -> DatasetReport {
+    let limit = example_token_limit(model);
+    let mut report = DatasetReport {
+        examples: 0,
+        issues: vec![],
+        estimated_tokens: 0,
+        estimated_cost_per_epoch: None,
+    };
+
+    for (index, line) in jsonl.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_number = index + 1;
+        report.examples += 1;
+
+        let example: TrainingExample = match serde_json::from_str(line) {
+            Ok(example) => example,
+            Err(err) => {
+                report.issues.push(DatasetIssue {
+                    line: line_number,
+                    kind: DatasetIssueKind::InvalidSchema(err.to_string()),
+                });
+                continue;
+            }
+        };
+
+        for kind in check_roles(&example.messages) {
+            report.issues.push(DatasetIssue {
+                line: line_number,
+                kind,
+            });
+        }
+
+        let tokens = estimate_message_tokens(&example.messages);
+        match limit {
+            Some(limit) if tokens > limit => {
+                report.issues.push(DatasetIssue {
+                    line: line_number,
+                    kind: DatasetIssueKind::TooManyTokens { tokens, limit },
+                });
+                report.estimated_tokens += limit;
+            }
+            _ => report.estimated_tokens += tokens,
+        }
+    }
+
+    report.estimated_cost_per_epoch = pricing.training_cost(model, report.estimated_tokens);
+    report
+}
+
+pub async fn validate_dataset_file(
+    path: impl AsRef<Path>,
+    model: &str,
+    pricing: &Pricing,
+) -> Result<DatasetReport, Box<dyn Error + Send + Sync>> {
+    let jsonl = tokio::fs::read_to_string(path).await?;
+    Ok(validate_dataset(&jsonl, model, pricing))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example(roles: &[ChatRole]) -> TrainingExample {
+        TrainingExample::new(
+            roles
+                .iter()
+                .map(|role| ChatMessage::new(*role, "Jenny and I are moving to LA"))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut dataset = TrainingDataset::default();
+        dataset.push(example(&[
+            ChatRole::System,
+            ChatRole::User,
+            ChatRole::Assistant,
+        ]));
+        dataset.push(example(&[ChatRole::User, ChatRole::Assistant]));
+
+        let jsonl = dataset.to_jsonl().expect("error writing dataset");
+        assert_eq!(jsonl.lines().count(), 2);
+
+        let report = validate_dataset(&jsonl, "gpt-3.5-turbo-0613", &Pricing::default());
+        assert!(report.is_valid(), "{:?}", report.issues);
+        assert_eq!(report.examples, 2);
+        // 4 tokens of overhead plus 7 of content per message, and 3 per example
+        assert_eq!(report.estimated_tokens, 3 * 11 + 3 + 2 * 11 + 3);
+        assert_eq!(
+            report.estimated_cost_per_epoch,
+            Some(report.estimated_tokens as f64 * 0.008 / 1000.0)
+        );
+    }
+
+    #[test]
+    fn test_invalid_examples() {
+        let mut dataset = TrainingDataset::default();
+        dataset.push(example(&[ChatRole::User, ChatRole::User]));
+        dataset.push(example(&[
+            ChatRole::Assistant,
+            ChatRole::System,
+            ChatRole::Assistant,
+        ]));
+        dataset.push(TrainingExample::new(vec![
+            ChatMessage::new(ChatRole::User, &"toad ".repeat(20000)),
+            ChatMessage::new(ChatRole::Assistant, "ribbit"),
+        ]));
+        let jsonl = dataset.to_jsonl().expect("error writing dataset")
+            + "{\"messages\": [{\"role\": \"narrator\", \"content\": \"hi\"}]}\n"
+            + "not json\n";
+
+        let report = validate_dataset(&jsonl, "gpt-3.5-turbo-0613", &Pricing::default());
+        let kinds: Vec<(usize, &DatasetIssueKind)> = report
+            .issues
+            .iter()
+            .map(|issue| (issue.line, &issue.kind))
+            .collect();
+
+        assert_eq!(report.examples, 5);
+        assert_eq!(kinds[0], (1, &DatasetIssueKind::NoAssistantMessage));
+        assert_eq!(
+            kinds[1],
+            (1, &DatasetIssueKind::RepeatedRole(ChatRole::User))
+        );
+        assert_eq!(kinds[2], (2, &DatasetIssueKind::SystemMessageNotFirst));
+        assert_eq!(kinds[3], (2, &DatasetIssueKind::FirstMessageNotUser));
+        assert!(matches!(
+            kinds[4],
+            (3, DatasetIssueKind::TooManyTokens { limit: 4096, .. })
+        ));
+        assert!(matches!(kinds[5], (4, DatasetIssueKind::InvalidSchema(_))));
+        assert!(matches!(kinds[6], (5, DatasetIssueKind::InvalidSchema(_))));
+        assert_eq!(kinds.len(), 7);
+    }
+
+    #[test]
+    fn test_function_calling_examples() {
+        let jsonl = [
+            serde_json::json!({
+                "messages": [
+                    { "role": "user", "content": "How warm is the north pond?" },
+                    {
+                        "role": "assistant",
+                        "content": null,
+                        "function_call": { "name": "get_pond_temperature", "arguments": "{\"pond\":\"north\"}" }
+                    },
+                    { "role": "function", "name": "get_pond_temperature", "content": "18.5" },
+                    { "role": "assistant", "content": "It's 18.5 degrees." }
+                ],
+                "functions": [{ "name": "get_pond_temperature", "parameters": { "type": "object" } }]
+            }),
+            serde_json::json!({
+                "messages": [
+                    { "role": "user", "content": "How warm is the north pond?" },
+                    {
+                        "role": "assistant",
+                        "content": null,
+                        "tool_calls": [{
+                            "id": "call_1",
+                            "type": "function",
+                            "function": { "name": "get_pond_temperature", "arguments": "{}" }
+                        }]
+                    },
+                    { "role": "tool", "tool_call_id": "call_1", "content": "18.5" },
+                    { "role": "assistant", "content": "It's 18.5 degrees." }
+                ]
+            }),
+            serde_json::json!({
+                "messages": [
+                    { "role": "user", "content": "How warm is the north pond?" },
+                    { "role": "assistant", "content": null }
+                ]
+            }),
+        ]
+        .iter()
+        .map(|example| example.to_string() + "\n")
+        .collect::<String>();
+
+        let report = validate_dataset(&jsonl, "gpt-4o-mini", &Pricing::default());
+        assert_eq!(report.examples, 3);
+        assert_eq!(
+            report.issues,
+            [DatasetIssue {
+                line: 3,
+                kind: DatasetIssueKind::MissingContent(ChatRole::Assistant)
+            }]
+        );
+    }
+}
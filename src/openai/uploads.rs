@@ -0,0 +1,454 @@
+use core::fmt;
+use std::error::Error;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::{stream, TryStreamExt};
+use md5::{Digest, Md5};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncReadExt;
+use tokio_util::sync::CancellationToken;
+
+use crate::OpenAIClient;
+
+use super::files::{FilePurpose, OpenAIFile};
+use super::multipart::{FormBuilder, UploadSource};
+
+/// The largest part the Uploads API accepts.
+pub const MAX_UPLOAD_PART_SIZE: usize = 64 * 1024 * 1024;
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateUploadOptions {
+    pub filename: String,
+    pub purpose: FilePurpose,
+    /// Total size of the file in bytes
+    pub bytes: u64,
+    pub mime_type: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum UploadStatus {
+    #[serde(rename = "pending")]
+    Pending,
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "cancelled")]
+    Cancelled,
+    #[serde(rename = "expired")]
+    Expired,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Upload {
+    pub id: String,
+    pub object: String,
+    pub bytes: u64,
+    pub created_at: u64,
+    pub filename: String,
+    pub purpose: FilePurpose,
+    pub status: UploadStatus,
+    pub expires_at: Option<u64>,
+    /// The created file, once the upload is completed
+    pub file: Option<OpenAIFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadPart {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub upload_id: String,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompleteUploadOptions {
+    /// In the order the parts appear in the file
+    pub part_ids: Vec<String>,
+    /// Hex MD5 of the whole file, checked by the server if set
+    pub md5: Option<String>,
+}
+
+/// Options for [`OpenAIClient::upload_large_file`].
+#[derive(Debug, Clone)]
+pub struct LargeUploadOptions {
+    pub path: PathBuf,
+    pub purpose: FilePurpose,
+    pub mime_type: String,
+    /// At most [`MAX_UPLOAD_PART_SIZE`]
+    pub part_size: usize,
+    /// How many parts are uploaded at once. Each one is held in memory while it uploads.
+    pub concurrency: usize,
+    /// How many times a failed part is retried before the upload is cancelled
+    pub max_retries: u32,
+    /// Doubled after every retry of a part
+    pub retry_interval: Duration,
+    /// Cancels the upload on OpenAI's side when triggered
+    pub cancel: Option<CancellationToken>,
+}
+
+impl LargeUploadOptions {
+    pub fn default(path: impl AsRef<Path>, purpose: FilePurpose, mime_type: &str) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+            purpose,
+            mime_type: mime_type.to_owned(),
+            part_size: MAX_UPLOAD_PART_SIZE,
+            concurrency: 4,
+            max_retries: 3,
+            retry_interval: Duration::from_secs(1),
+            cancel: None,
+        }
+    }
+}
+
+/// Returned (boxed) when a large upload was cancelled through [`LargeUploadOptions::cancel`].
+#[derive(Debug, Clone)]
+pub struct UploadCancelled {
+    pub upload_id: String,
+}
+
+impl Display for UploadCancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "upload {} was cancelled", self.upload_id)
+    }
+}
+
+impl Error for UploadCancelled {}
+
+/// Doubles `interval` for every earlier retry, saturating rather than overflowing.
+fn retry_delay(interval: Duration, attempt: u32) -> Duration {
+    interval.saturating_mul(2u32.saturating_pow(attempt))
+}
+
+impl OpenAIClient {
+    pub async fn create_upload(
+        &self,
+        opts: &CreateUploadOptions,
+    ) -> Result<Upload, Box<dyn Error + Send + Sync>> {
+        Ok(self.post_json("/uploads", opts).await?.data)
+    }
+
+    pub async fn add_upload_part(
+        &self,
+        upload_id: &str,
+        data: UploadSource,
+    ) -> Result<UploadPart, Box<dyn Error + Send + Sync>> {
+        let form = FormBuilder::new().file("data", "data", None, data).await?;
+        Ok(self
+            .post_multipart(&format!("/uploads/{upload_id}/parts"), form.build())
+            .await?
+            .data)
+    }
+
+    pub async fn complete_upload(
+        &self,
+        upload_id: &str,
+        opts: &CompleteUploadOptions,
+    ) -> Result<Upload, Box<dyn Error + Send + Sync>> {
+        Ok(self
+            .post_json(&format!("/uploads/{upload_id}/complete"), opts)
+            .await?
+            .data)
+    }
+
+    pub async fn cancel_upload(
+        &self,
+        upload_id: &str,
+    ) -> Result<Upload, Box<dyn Error + Send + Sync>> {
+        let request = self.request(Method::POST, &format!("/uploads/{upload_id}/cancel"));
+        Ok(self.send_json(request).await?.data)
+    }
+
+    /// Uploads a file of any size through the Uploads API: the file is read from disk in parts,
+    /// the parts are uploaded concurrently and retried on failure, and the upload is completed
+    /// with an MD5 checksum. If a part fails for good or `opts.cancel` is triggered, the upload
+    /// is cancelled.
+    pub async fn upload_large_file(
+        &self,
+        opts: &LargeUploadOptions,
+    ) -> Result<OpenAIFile, Box<dyn Error + Send + Sync>> {
+        let filename = opts
+            .path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or("upload path has no file name")?;
+        let bytes = tokio::fs::metadata(&opts.path).await?.len();
+        let upload = self
+            .create_upload(&CreateUploadOptions {
+                filename: filename.to_owned(),
+                purpose: opts.purpose.clone(),
+                bytes,
+                mime_type: opts.mime_type.clone(),
+            })
+            .await?;
+
+        let parts = self.upload_parts(&upload.id, opts);
+        let result = match &opts.cancel {
+            Some(cancel) => tokio::select! {
+                result = parts => result,
+                _ = cancel.cancelled() => Err(Box::new(UploadCancelled {
+                    upload_id: upload.id.clone(),
+                }) as Box<dyn Error + Send + Sync>),
+            },
+            None => parts.await,
+        };
+        let (part_ids, md5) = match result {
+            Ok(parts) => parts,
+            Err(err) => {
+                // The original error is more useful than one from cancelling
+                let _ = self.cancel_upload(&upload.id).await;
+                return Err(err);
+            }
+        };
+
+        let upload = self
+            .complete_upload(
+                &upload.id,
+                &CompleteUploadOptions {
+                    part_ids,
+                    md5: Some(md5),
+                },
+            )
+            .await?;
+        Ok(upload.file.ok_or("completed upload has no file")?)
+    }
+
+    /// Returns the part ids in file order and the hex MD5 of the file.
+    async fn upload_parts(
+        &self,
+        upload_id: &str,
+        opts: &LargeUploadOptions,
+    ) -> Result<(Vec<String>, String), Box<dyn Error + Send + Sync>> {
+        let part_size = opts.part_size.clamp(1, MAX_UPLOAD_PART_SIZE);
+        let file = tokio::fs::File::open(&opts.path).await?;
+        // Parts are read in order, so the whole file can be hashed as it is read
+        let hasher = Mutex::new(Md5::new());
+        let hasher_ref = &hasher;
+
+        let chunks = stream::try_unfold(file, move |mut file| async move {
+            let mut chunk = Vec::with_capacity(part_size);
+            (&mut file)
+                .take(part_size as u64)
+                .read_to_end(&mut chunk)
+                .await?;
+            if chunk.is_empty() {
+                return Ok::<_, Box<dyn Error + Send + Sync>>(None);
+            }
+            hasher_ref.lock().unwrap().update(&chunk);
+            Ok(Some((Bytes::from(chunk), file)))
+        });
+        let part_ids: Vec<String> = chunks
+            .map_ok(|chunk| self.add_upload_part_with_retry(upload_id, chunk, opts))
+            .try_buffered(opts.concurrency.max(1))
+            .try_collect()
+            .await?;
+
+        let md5 = format!("{:x}", hasher.into_inner().unwrap().finalize());
+        Ok((part_ids, md5))
+    }
+
+    async fn add_upload_part_with_retry(
+        &self,
+        upload_id: &str,
+        chunk: Bytes,
+        opts: &LargeUploadOptions,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let mut attempt = 0;
+        loop {
+            match self.add_upload_part(upload_id, chunk.clone().into()).await {
+                Ok(part) => return Ok(part.id),
+                Err(err) if attempt >= opts.max_retries => return Err(err),
+                Err(_) => {
+                    tokio::time::sleep(retry_delay(opts.retry_interval, attempt)).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::{json, Value};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+    fn upload_json(status: &str) -> Value {
+        let mut upload = json!({
+            "id": "upload-1",
+            "object": "upload",
+            "bytes": 10,
+            "created_at": 1719184911,
+            "filename": "pond.jsonl",
+            "purpose": "fine-tune",
+            "status": status,
+            "expires_at": 1719127296
+        });
+        if status == "completed" {
+            upload["file"] = json!({
+                "id": "file-1",
+                "object": "file",
+                "bytes": 10,
+                "created_at": 1719186911,
+                "filename": "pond.jsonl",
+                "purpose": "fine-tune"
+            });
+        }
+        upload
+    }
+
+    /// Names each part after its contents, so the test can check they are completed in order.
+    struct PartResponder;
+
+    impl Respond for PartResponder {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let body = String::from_utf8_lossy(&request.body);
+            let content = body
+                .split("\r\n\r\n")
+                .nth(1)
+                .and_then(|rest| rest.split("\r\n").next())
+                .unwrap_or_default();
+            ResponseTemplate::new(200).set_body_json(json!({
+                "id": format!("part-{content}"),
+                "object": "upload.part",
+                "created_at": 1719185911,
+                "upload_id": "upload-1"
+            }))
+        }
+    }
+
+    fn write_temp_file(name: &str, content: &[u8]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("openai-client-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("error creating temp dir");
+        let path = dir.join(name);
+        std::fs::write(&path, content).expect("error writing temp file");
+        path
+    }
+
+    #[tokio::test]
+    pub async fn test_upload_large_file() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/uploads"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(upload_json("pending")))
+            .mount(&server)
+            .await;
+        // The first part request fails and has to be retried
+        Mock::given(method("POST"))
+            .and(path("/uploads/upload-1/parts"))
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/uploads/upload-1/parts"))
+            .respond_with(PartResponder)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/uploads/upload-1/complete"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(upload_json("completed")))
+            .mount(&server)
+            .await;
+
+        let content = b"aaaabbbbcc";
+        let file_path = write_temp_file("pond.jsonl", content);
+        let mut opts = LargeUploadOptions::default(&file_path, FilePurpose::FineTune, "text/jsonl");
+        opts.part_size = 4;
+        opts.retry_interval = Duration::from_millis(5);
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let file = client
+            .upload_large_file(&opts)
+            .await
+            .expect("error uploading file");
+        assert_eq!(file.id, "file-1");
+
+        let requests = server.received_requests().await.expect("no requests");
+        let create: Value = serde_json::from_slice(&requests[0].body).expect("invalid body");
+        assert_eq!(
+            create,
+            json!({
+                "filename": "pond.jsonl",
+                "purpose": "fine-tune",
+                "bytes": 10,
+                "mime_type": "text/jsonl"
+            })
+        );
+        let complete = requests.last().expect("no requests");
+        assert_eq!(complete.url.path(), "/uploads/upload-1/complete");
+        let complete: Value = serde_json::from_slice(&complete.body).expect("invalid body");
+        assert_eq!(
+            complete,
+            json!({
+                "part_ids": ["part-aaaa", "part-bbbb", "part-cc"],
+                "md5": format!("{:x}", Md5::digest(content))
+            })
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_upload_large_file_cancels_on_failure() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/uploads"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(upload_json("pending")))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/uploads/upload-1/parts"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/uploads/upload-1/cancel"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(upload_json("cancelled")))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let file_path = write_temp_file("lily.jsonl", b"aaaabbbb");
+        let mut opts = LargeUploadOptions::default(&file_path, FilePurpose::FineTune, "text/jsonl");
+        opts.part_size = 4;
+        opts.max_retries = 1;
+        opts.retry_interval = Duration::from_millis(5);
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let err = client
+            .upload_large_file(&opts)
+            .await
+            .expect_err("upload succeeded");
+        assert!(err.downcast_ref::<UploadCancelled>().is_none());
+
+        // Cancelling through the token
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        opts.cancel = Some(cancel);
+        let err = client
+            .upload_large_file(&opts)
+            .await
+            .expect_err("upload succeeded");
+        let cancelled = err
+            .downcast_ref::<UploadCancelled>()
+            .expect("expected UploadCancelled");
+        assert_eq!(cancelled.upload_id, "upload-1");
+    }
+
+    #[test]
+    fn test_retry_delay_saturates() {
+        let interval = Duration::from_secs(1);
+        assert_eq!(retry_delay(interval, 0), interval);
+        assert_eq!(retry_delay(interval, 3), Duration::from_secs(8));
+        assert_eq!(retry_delay(interval, 40), interval * u32::MAX);
+        assert_eq!(retry_delay(Duration::MAX, 2), Duration::MAX);
+    }
+}
@@ -1,9 +1,213 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
 use serde::{Deserialize, Serialize};
 
+use super::pricing::Pricing;
+
 #[serde_with::skip_serializing_none]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Usage {
     pub prompt_tokens: u64,
     pub completion_tokens: Option<u64>,
     pub total_tokens: u64,
 }
+
+/// Running totals for one model, tag or user.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Cost in US dollars
+    pub cost: f64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, prompt_tokens: u64, completion_tokens: u64, cost: f64) {
+        self.requests += 1;
+        self.prompt_tokens += prompt_tokens;
+        self.completion_tokens += completion_tokens;
+        self.cost += cost;
+    }
+}
+
+/// A point-in-time copy of everything a [`UsageTracker`] has recorded.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageSnapshot {
+    pub total: UsageTotals,
+    pub by_model: HashMap<String, UsageTotals>,
+    pub by_tag: HashMap<String, UsageTotals>,
+    pub by_user: HashMap<String, UsageTotals>,
+}
+
+impl UsageSnapshot {
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// One row per model, tag and user, plus a `total` row, with the header
+    /// `scope,key,requests,prompt_tokens,completion_tokens,cost`.
+    pub fn to_csv(&self) -> String {
+        let mut csv = "scope,key,requests,prompt_tokens,completion_tokens,cost\n".to_owned();
+        let mut push_row = |scope: &str, key: &str, totals: &UsageTotals| {
+            csv += &format!(
+                "{scope},{},{},{},{},{:.6}\n",
+                csv_field(key),
+                totals.requests,
+                totals.prompt_tokens,
+                totals.completion_tokens,
+                totals.cost
+            );
+        };
+
+        push_row("total", "", &self.total);
+        for (scope, totals) in [
+            ("model", &self.by_model),
+            ("tag", &self.by_tag),
+            ("user", &self.by_user),
+        ] {
+            let mut keys: Vec<&String> = totals.keys().collect();
+            keys.sort();
+            for key in keys {
+                push_row(scope, key, &totals[key]);
+            }
+        }
+        csv
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Aggregates the [`Usage`] returned by chat, completion and embedding requests. Attach one to
+/// [`crate::OpenAIClient::usage_tracker`] and every successful request is recorded against its
+/// model, the client's tag and the request's `user` field.
+#[derive(Debug)]
+pub struct UsageTracker {
+    pricing: Pricing,
+    snapshot: Mutex<UsageSnapshot>,
+}
+
+impl Default for UsageTracker {
+    fn default() -> Self {
+        Self::new(Pricing::default())
+    }
+}
+
+impl UsageTracker {
+    pub fn new(pricing: Pricing) -> Self {
+        Self {
+            pricing,
+            snapshot: Mutex::new(UsageSnapshot::default()),
+        }
+    }
+
+    pub fn pricing(&self) -> &Pricing {
+        &self.pricing
+    }
+
+    pub fn record(&self, model: &str, tag: Option<&str>, user: Option<&str>, usage: &Usage) {
+        let prompt_tokens = usage.prompt_tokens;
+        let completion_tokens = usage.completion_tokens.unwrap_or(0);
+        let cost = self.pricing.cost(model, prompt_tokens, completion_tokens);
+
+        let mut snapshot = self.snapshot.lock().expect("usage tracker lock poisoned");
+        snapshot.total.add(prompt_tokens, completion_tokens, cost);
+        snapshot.by_model.entry(model.to_owned()).or_default().add(
+            prompt_tokens,
+            completion_tokens,
+            cost,
+        );
+        if let Some(tag) = tag {
+            snapshot.by_tag.entry(tag.to_owned()).or_default().add(
+                prompt_tokens,
+                completion_tokens,
+                cost,
+            );
+        }
+        if let Some(user) = user {
+            snapshot.by_user.entry(user.to_owned()).or_default().add(
+                prompt_tokens,
+                completion_tokens,
+                cost,
+            );
+        }
+    }
+
+    pub fn snapshot(&self) -> UsageSnapshot {
+        self.snapshot
+            .lock()
+            .expect("usage tracker lock poisoned")
+            .clone()
+    }
+
+    /// Returns everything recorded so far and starts again from zero.
+    pub fn reset(&self) -> UsageSnapshot {
+        std::mem::take(&mut *self.snapshot.lock().expect("usage tracker lock poisoned"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openai::pricing::ModelPricing;
+
+    fn usage(prompt_tokens: u64, completion_tokens: Option<u64>) -> Usage {
+        Usage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens.unwrap_or(0),
+        }
+    }
+
+    #[test]
+    fn test_record_usage() {
+        let mut pricing = Pricing::empty();
+        pricing.set("chat-model", ModelPricing::new(1.0, 2.0));
+        let tracker = UsageTracker::new(pricing);
+
+        tracker.record(
+            "chat-model",
+            Some("search"),
+            Some("jenny"),
+            &usage(1000, Some(500)),
+        );
+        tracker.record("chat-model", Some("search"), None, &usage(1000, Some(0)));
+        tracker.record("embedding-model", None, Some("jenny"), &usage(200, None));
+
+        let snapshot = tracker.snapshot();
+        assert_eq!(snapshot.total.requests, 3);
+        assert_eq!(snapshot.total.prompt_tokens, 2200);
+        assert_eq!(snapshot.total.cost, 3.0);
+        assert_eq!(snapshot.by_model["chat-model"].completion_tokens, 500);
+        assert_eq!(snapshot.by_model["embedding-model"].cost, 0.0);
+        assert_eq!(snapshot.by_tag["search"].requests, 2);
+        assert_eq!(snapshot.by_user["jenny"].prompt_tokens, 1200);
+
+        let reset = tracker.reset();
+        assert_eq!(reset.total.requests, 3);
+        assert_eq!(tracker.snapshot().total, UsageTotals::default());
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let tracker = UsageTracker::new(Pricing::empty());
+        tracker.record("model", Some("team, a"), None, &usage(10, Some(5)));
+
+        let csv = tracker.snapshot().to_csv();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            rows[0],
+            "scope,key,requests,prompt_tokens,completion_tokens,cost"
+        );
+        assert_eq!(rows[1], "total,,1,10,5,0.000000");
+        assert_eq!(rows[2], "model,model,1,10,5,0.000000");
+        assert_eq!(rows[3], "tag,\"team, a\",1,10,5,0.000000");
+    }
+}
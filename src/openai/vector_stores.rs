@@ -0,0 +1,663 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::OpenAIClient;
+
+use super::deletion::DeletionStatus;
+use super::files::{FilePurpose, UploadFileOptions};
+use super::list::{ListOptions, ListOrder, ListResponse};
+use super::multipart::UploadSource;
+use super::poll::{Backoff, PollOptions};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VectorStoreFileCounts {
+    pub in_progress: u64,
+    pub completed: u64,
+    pub failed: u64,
+    pub cancelled: u64,
+    pub total: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum VectorStoreStatus {
+    #[serde(rename = "expired")]
+    Expired,
+    #[serde(rename = "in_progress")]
+    InProgress,
+    #[serde(rename = "completed")]
+    Completed,
+}
+
+/// Expires the store `days` after it was last used.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpiresAfter {
+    /// Currently always `last_active_at`
+    pub anchor: String,
+    pub days: u32,
+}
+
+impl ExpiresAfter {
+    pub fn days(days: u32) -> Self {
+        Self {
+            anchor: "last_active_at".to_owned(),
+            days,
+        }
+    }
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorStore {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub name: Option<String>,
+    pub usage_bytes: u64,
+    pub file_counts: VectorStoreFileCounts,
+    pub status: VectorStoreStatus,
+    pub expires_after: Option<ExpiresAfter>,
+    pub expires_at: Option<u64>,
+    pub last_active_at: Option<u64>,
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CreateVectorStoreOptions {
+    pub name: Option<String>,
+    pub file_ids: Option<Vec<String>>,
+    pub expires_after: Option<ExpiresAfter>,
+    pub chunking_strategy: Option<Value>,
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+/// Only the fields that are set are changed.
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ModifyVectorStoreOptions {
+    pub name: Option<String>,
+    pub expires_after: Option<ExpiresAfter>,
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum VectorStoreFileStatus {
+    #[serde(rename = "in_progress")]
+    InProgress,
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "cancelled")]
+    Cancelled,
+    #[serde(rename = "failed")]
+    Failed,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorStoreFile {
+    pub id: String,
+    pub object: String,
+    pub usage_bytes: u64,
+    pub created_at: u64,
+    pub vector_store_id: String,
+    pub status: VectorStoreFileStatus,
+    pub last_error: Option<Value>,
+    pub chunking_strategy: Option<Value>,
+    pub attributes: Option<HashMap<String, Value>>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateVectorStoreFileOptions {
+    pub file_id: String,
+    pub chunking_strategy: Option<Value>,
+    /// Searchable with [`SearchFilter`]s. Values are strings, numbers or booleans.
+    pub attributes: Option<HashMap<String, Value>>,
+}
+
+impl CreateVectorStoreFileOptions {
+    pub fn default(file_id: &str) -> Self {
+        Self {
+            file_id: file_id.to_owned(),
+            chunking_strategy: None,
+            attributes: None,
+        }
+    }
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListVectorStoreFilesOptions {
+    pub limit: Option<u32>,
+    pub order: Option<ListOrder>,
+    pub after: Option<String>,
+    pub before: Option<String>,
+    pub filter: Option<VectorStoreFileStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorStoreFileBatch {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub vector_store_id: String,
+    pub status: VectorStoreFileStatus,
+    pub file_counts: VectorStoreFileCounts,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateVectorStoreFileBatchOptions {
+    pub file_ids: Vec<String>,
+    pub chunking_strategy: Option<Value>,
+    /// Applied to every file in the batch
+    pub attributes: Option<HashMap<String, Value>>,
+}
+
+impl CreateVectorStoreFileBatchOptions {
+    pub fn default(file_ids: Vec<String>) -> Self {
+        Self {
+            file_ids,
+            chunking_strategy: None,
+            attributes: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ComparisonOperator {
+    #[serde(rename = "eq")]
+    Eq,
+    #[serde(rename = "ne")]
+    Ne,
+    #[serde(rename = "gt")]
+    Gt,
+    #[serde(rename = "gte")]
+    Gte,
+    #[serde(rename = "lt")]
+    Lt,
+    #[serde(rename = "lte")]
+    Lte,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CompoundOperator {
+    #[serde(rename = "and")]
+    And,
+    #[serde(rename = "or")]
+    Or,
+}
+
+/// Filters search results on file attributes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SearchFilter {
+    Comparison {
+        key: String,
+        #[serde(rename = "type")]
+        operator: ComparisonOperator,
+        value: Value,
+    },
+    Compound {
+        #[serde(rename = "type")]
+        operator: CompoundOperator,
+        filters: Vec<SearchFilter>,
+    },
+}
+
+impl SearchFilter {
+    pub fn compare(key: &str, operator: ComparisonOperator, value: impl Into<Value>) -> Self {
+        SearchFilter::Comparison {
+            key: key.to_owned(),
+            operator,
+            value: value.into(),
+        }
+    }
+
+    pub fn eq(key: &str, value: impl Into<Value>) -> Self {
+        Self::compare(key, ComparisonOperator::Eq, value)
+    }
+
+    pub fn and(filters: Vec<SearchFilter>) -> Self {
+        SearchFilter::Compound {
+            operator: CompoundOperator::And,
+            filters,
+        }
+    }
+
+    pub fn or(filters: Vec<SearchFilter>) -> Self {
+        SearchFilter::Compound {
+            operator: CompoundOperator::Or,
+            filters,
+        }
+    }
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RankingOptions {
+    pub ranker: Option<String>,
+    /// Results scoring below this (0 to 1) are dropped
+    pub score_threshold: Option<f64>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchVectorStoreOptions {
+    pub query: String,
+    pub max_num_results: Option<u32>,
+    pub filters: Option<SearchFilter>,
+    pub ranking_options: Option<RankingOptions>,
+    pub rewrite_query: Option<bool>,
+}
+
+impl SearchVectorStoreOptions {
+    pub fn default(query: &str) -> Self {
+        Self {
+            query: query.to_owned(),
+            max_num_results: None,
+            filters: None,
+            ranking_options: None,
+            rewrite_query: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResultContent {
+    #[serde(rename = "type")]
+    pub content_type: String,
+    pub text: String,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub file_id: String,
+    pub filename: String,
+    pub score: f64,
+    pub attributes: Option<HashMap<String, Value>>,
+    pub content: Vec<SearchResultContent>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub object: String,
+    /// The queries actually run, which differ from the input if `rewrite_query` was set
+    #[serde(default)]
+    pub search_query: Vec<String>,
+    pub data: Vec<SearchResult>,
+    #[serde(default)]
+    pub has_more: bool,
+    pub next_page: Option<String>,
+}
+
+impl OpenAIClient {
+    pub async fn create_vector_store(
+        &self,
+        opts: &CreateVectorStoreOptions,
+    ) -> Result<VectorStore, Box<dyn Error + Send + Sync>> {
+        let request = self.beta_request(Method::POST, "/vector_stores").json(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn list_vector_stores(
+        &self,
+        opts: &ListOptions,
+    ) -> Result<ListResponse<VectorStore>, Box<dyn Error + Send + Sync>> {
+        let request = self.beta_request(Method::GET, "/vector_stores").query(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn get_vector_store(
+        &self,
+        vector_store_id: &str,
+    ) -> Result<VectorStore, Box<dyn Error + Send + Sync>> {
+        let request = self.beta_request(Method::GET, &format!("/vector_stores/{vector_store_id}"));
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn modify_vector_store(
+        &self,
+        vector_store_id: &str,
+        opts: &ModifyVectorStoreOptions,
+    ) -> Result<VectorStore, Box<dyn Error + Send + Sync>> {
+        let request = self
+            .beta_request(Method::POST, &format!("/vector_stores/{vector_store_id}"))
+            .json(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn delete_vector_store(
+        &self,
+        vector_store_id: &str,
+    ) -> Result<DeletionStatus, Box<dyn Error + Send + Sync>> {
+        let request =
+            self.beta_request(Method::DELETE, &format!("/vector_stores/{vector_store_id}"));
+        Ok(self.send_json(request).await?.data)
+    }
+
+    /// Attaches an uploaded file to a vector store. The file is indexed in the background; poll
+    /// [`OpenAIClient::get_vector_store_file`] or use a file batch to wait for it.
+    pub async fn create_vector_store_file(
+        &self,
+        vector_store_id: &str,
+        opts: &CreateVectorStoreFileOptions,
+    ) -> Result<VectorStoreFile, Box<dyn Error + Send + Sync>> {
+        let request = self
+            .beta_request(
+                Method::POST,
+                &format!("/vector_stores/{vector_store_id}/files"),
+            )
+            .json(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn list_vector_store_files(
+        &self,
+        vector_store_id: &str,
+        opts: &ListVectorStoreFilesOptions,
+    ) -> Result<ListResponse<VectorStoreFile>, Box<dyn Error + Send + Sync>> {
+        let request = self
+            .beta_request(
+                Method::GET,
+                &format!("/vector_stores/{vector_store_id}/files"),
+            )
+            .query(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn get_vector_store_file(
+        &self,
+        vector_store_id: &str,
+        file_id: &str,
+    ) -> Result<VectorStoreFile, Box<dyn Error + Send + Sync>> {
+        let request = self.beta_request(
+            Method::GET,
+            &format!("/vector_stores/{vector_store_id}/files/{file_id}"),
+        );
+        Ok(self.send_json(request).await?.data)
+    }
+
+    /// Detaches a file from a vector store. The file itself is not deleted; use
+    /// [`OpenAIClient::delete_file`] for that.
+    pub async fn delete_vector_store_file(
+        &self,
+        vector_store_id: &str,
+        file_id: &str,
+    ) -> Result<DeletionStatus, Box<dyn Error + Send + Sync>> {
+        let request = self.beta_request(
+            Method::DELETE,
+            &format!("/vector_stores/{vector_store_id}/files/{file_id}"),
+        );
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn create_vector_store_file_batch(
+        &self,
+        vector_store_id: &str,
+        opts: &CreateVectorStoreFileBatchOptions,
+    ) -> Result<VectorStoreFileBatch, Box<dyn Error + Send + Sync>> {
+        let request = self
+            .beta_request(
+                Method::POST,
+                &format!("/vector_stores/{vector_store_id}/file_batches"),
+            )
+            .json(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn get_vector_store_file_batch(
+        &self,
+        vector_store_id: &str,
+        batch_id: &str,
+    ) -> Result<VectorStoreFileBatch, Box<dyn Error + Send + Sync>> {
+        let request = self.beta_request(
+            Method::GET,
+            &format!("/vector_stores/{vector_store_id}/file_batches/{batch_id}"),
+        );
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn cancel_vector_store_file_batch(
+        &self,
+        vector_store_id: &str,
+        batch_id: &str,
+    ) -> Result<VectorStoreFileBatch, Box<dyn Error + Send + Sync>> {
+        let request = self.beta_request(
+            Method::POST,
+            &format!("/vector_stores/{vector_store_id}/file_batches/{batch_id}/cancel"),
+        );
+        Ok(self.send_json(request).await?.data)
+    }
+
+    pub async fn list_vector_store_file_batch_files(
+        &self,
+        vector_store_id: &str,
+        batch_id: &str,
+        opts: &ListVectorStoreFilesOptions,
+    ) -> Result<ListResponse<VectorStoreFile>, Box<dyn Error + Send + Sync>> {
+        let request = self
+            .beta_request(
+                Method::GET,
+                &format!("/vector_stores/{vector_store_id}/file_batches/{batch_id}/files"),
+            )
+            .query(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+
+    /// Polls a file batch until every file in it has been indexed, failed or been cancelled.
+    /// Check the batch's `file_counts` to see how many failed.
+    pub async fn wait_for_vector_store_file_batch(
+        &self,
+        vector_store_id: &str,
+        batch_id: &str,
+        poll: &PollOptions,
+    ) -> Result<VectorStoreFileBatch, Box<dyn Error + Send + Sync>> {
+        let mut backoff = Backoff::new(poll);
+        loop {
+            let batch = self
+                .get_vector_store_file_batch(vector_store_id, batch_id)
+                .await?;
+            if batch.status != VectorStoreFileStatus::InProgress {
+                return Ok(batch);
+            }
+            backoff.wait().await?;
+        }
+    }
+
+    /// Uploads `files`, as pairs of file names and contents, for file search, adds them to a
+    /// vector store in one batch and waits for them to be indexed.
+    pub async fn upload_to_vector_store(
+        &self,
+        vector_store_id: &str,
+        files: Vec<(&str, UploadSource)>,
+        poll: &PollOptions,
+    ) -> Result<VectorStoreFileBatch, Box<dyn Error + Send + Sync>> {
+        let mut file_ids = vec![];
+        for (file_name, file) in files {
+            let file = UploadFileOptions::default(file_name, FilePurpose::Assistants, file);
+            file_ids.push(self.upload_file(file).await?.id);
+        }
+        let batch = self
+            .create_vector_store_file_batch(
+                vector_store_id,
+                &CreateVectorStoreFileBatchOptions::default(file_ids),
+            )
+            .await?;
+        self.wait_for_vector_store_file_batch(vector_store_id, &batch.id, poll)
+            .await
+    }
+
+    pub async fn search_vector_store(
+        &self,
+        vector_store_id: &str,
+        opts: &SearchVectorStoreOptions,
+    ) -> Result<SearchResults, Box<dyn Error + Send + Sync>> {
+        let request = self
+            .beta_request(
+                Method::POST,
+                &format!("/vector_stores/{vector_store_id}/search"),
+            )
+            .json(opts);
+        Ok(self.send_json(request).await?.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+    use std::time::Duration;
+    use wiremock::matchers::{body_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn file_batch_json(status: &str, completed: u64) -> Value {
+        json!({
+            "id": "vsfb-1",
+            "object": "vector_store.file_batch",
+            "created_at": 1699061776,
+            "vector_store_id": "vs-1",
+            "status": status,
+            "file_counts": {
+                "in_progress": 2 - completed,
+                "completed": completed,
+                "failed": 0,
+                "cancelled": 0,
+                "total": 2
+            }
+        })
+    }
+
+    #[tokio::test]
+    pub async fn test_upload_to_vector_store() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/files"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "file-1",
+                "object": "file",
+                "bytes": 5,
+                "created_at": 1699061776,
+                "filename": "pond.md",
+                "purpose": "assistants"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/vector_stores/vs-1/file_batches"))
+            .and(header("OpenAI-Beta", "assistants=v2"))
+            .and(body_json(json!({ "file_ids": ["file-1", "file-1"] })))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(file_batch_json("in_progress", 0)),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/vector_stores/vs-1/file_batches/vsfb-1"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(file_batch_json("in_progress", 1)),
+            )
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/vector_stores/vs-1/file_batches/vsfb-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(file_batch_json("completed", 2)))
+            .mount(&server)
+            .await;
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let files = vec![
+            ("pond.md", b"ribbit".to_vec().into()),
+            ("lily.md", b"ribbit".to_vec().into()),
+        ];
+        let poll = PollOptions {
+            initial_interval: Duration::from_millis(5),
+            ..Default::default()
+        };
+        let batch = client
+            .upload_to_vector_store("vs-1", files, &poll)
+            .await
+            .expect("error uploading to vector store");
+        assert_eq!(batch.status, VectorStoreFileStatus::Completed);
+        assert_eq!(batch.file_counts.completed, 2);
+
+        let requests = server.received_requests().await.expect("no requests");
+        let upload = String::from_utf8_lossy(&requests[0].body);
+        assert!(upload.contains("name=\"purpose\"\r\n\r\nassistants\r\n"));
+    }
+
+    #[tokio::test]
+    pub async fn test_search_vector_store() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/vector_stores/vs-1/search"))
+            .and(body_json(json!({
+                "query": "how warm is the pond?",
+                "max_num_results": 5,
+                "filters": {
+                    "type": "and",
+                    "filters": [
+                        { "key": "pond", "type": "eq", "value": "north" },
+                        { "key": "year", "type": "gte", "value": 2023 }
+                    ]
+                },
+                "ranking_options": { "score_threshold": 0.5 }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "object": "vector_store.search_results.page",
+                "search_query": ["pond temperature"],
+                "data": [{
+                    "file_id": "file-1",
+                    "filename": "pond.md",
+                    "score": 0.82,
+                    "attributes": { "pond": "north", "year": 2024 },
+                    "content": [{ "type": "text", "text": "The north pond is 18C." }]
+                }],
+                "has_more": false,
+                "next_page": null
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenAIClient::new("test-key", &server.uri());
+        let mut opts = SearchVectorStoreOptions::default("how warm is the pond?");
+        opts.max_num_results = Some(5);
+        opts.filters = Some(SearchFilter::and(vec![
+            SearchFilter::eq("pond", "north"),
+            SearchFilter::compare("year", ComparisonOperator::Gte, 2023),
+        ]));
+        opts.ranking_options = Some(RankingOptions {
+            score_threshold: Some(0.5),
+            ..Default::default()
+        });
+        let results = client
+            .search_vector_store("vs-1", &opts)
+            .await
+            .expect("error searching vector store");
+
+        assert_eq!(results.search_query, ["pond temperature"]);
+        assert_eq!(results.data[0].content[0].text, "The north pond is 18C.");
+
+        let filter: SearchFilter = serde_json::from_value(json!({
+            "type": "or",
+            "filters": [{ "key": "pond", "type": "ne", "value": "south" }]
+        }))
+        .expect("error parsing filter");
+        assert_eq!(
+            filter,
+            SearchFilter::or(vec![SearchFilter::compare(
+                "pond",
+                ComparisonOperator::Ne,
+                "south"
+            )])
+        );
+    }
+}
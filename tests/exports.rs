@@ -0,0 +1,215 @@
+use openai_client::{
+    BatchBuilder, ChatMessage, ChatOptions, ChatRole, CreateEmbeddingsOptions, DeletionStatus,
+    FilePurpose, InputFlagged, LargeUploadOptions, ListFilesOptions, ListOptions, ListOrder,
+    ListResponse, ModerationGate, OpenAIClient, OpenAIFile, OpenAIGetModelsResponse, OpenAIModel,
+    UploadFileOptions, UploadSource,
+};
+use serde_json::json;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn file_json(id: &str) -> serde_json::Value {
+    json!({
+        "id": id,
+        "object": "file",
+        "bytes": 6,
+        "created_at": 1677610602,
+        "filename": "pond.md",
+        "purpose": "assistants"
+    })
+}
+
+#[tokio::test]
+pub async fn test_files_api_from_outside_crate() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/files"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(file_json("file-1")))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/files"))
+        .and(query_param("purpose", "assistants"))
+        .and(query_param("order", "asc"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "object": "list",
+            "data": [file_json("file-1")],
+            "has_more": false
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/fine_tuning/jobs"))
+        .and(query_param("limit", "1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "object": "list",
+            "data": [],
+            "has_more": false
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/files/file-1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "file-1",
+            "object": "file",
+            "deleted": true
+        })))
+        .mount(&server)
+        .await;
+
+    let client = OpenAIClient::new("test-key", &server.uri());
+    let opts = UploadFileOptions::default(
+        "pond.md",
+        FilePurpose::Assistants,
+        UploadSource::from(b"ribbit".to_vec()),
+    );
+    let file: OpenAIFile = client
+        .upload_file(opts)
+        .await
+        .expect("error uploading file");
+    assert_eq!(file.purpose, FilePurpose::Assistants);
+
+    let opts = ListFilesOptions {
+        purpose: Some(FilePurpose::Assistants),
+        order: Some(ListOrder::Asc),
+        ..Default::default()
+    };
+    let files: ListResponse<OpenAIFile> = client.list_files(&opts).await.expect("error listing");
+    assert_eq!(files.data[0].id, "file-1");
+
+    let opts = ListOptions {
+        limit: Some(1),
+        ..Default::default()
+    };
+    let jobs = client
+        .list_fine_tuning_jobs(&opts)
+        .await
+        .expect("error listing jobs");
+    assert!(jobs.data.is_empty());
+
+    let deleted: DeletionStatus = client.delete_file("file-1").await.expect("error deleting");
+    assert!(deleted.deleted);
+}
+
+#[test]
+fn test_large_upload_options_from_outside_crate() {
+    let opts = LargeUploadOptions::default("assets/toad.png", FilePurpose::Vision, "image/png");
+    assert_eq!(opts.purpose, FilePurpose::Vision);
+}
+
+#[test]
+fn test_batches_from_outside_crate() {
+    let mut chat: BatchBuilder<ChatOptions> = BatchBuilder::default();
+    chat.push(
+        "greeting",
+        ChatOptions::default(
+            "gpt-4o-mini",
+            vec![ChatMessage::new(ChatRole::User, "say ribbit")],
+            20,
+        ),
+    );
+    let jsonl = chat.to_jsonl().expect("error writing chat batch");
+    let line: serde_json::Value = serde_json::from_str(jsonl.trim()).expect("invalid line");
+    assert_eq!(line["url"], "/v1/chat/completions");
+    assert_eq!(line["body"]["messages"][0]["content"], "say ribbit");
+
+    let mut embeddings: BatchBuilder<CreateEmbeddingsOptions> = BatchBuilder::default();
+    embeddings.push(
+        "doc-1",
+        CreateEmbeddingsOptions::default("text-embedding-3-small", vec!["toad".to_owned()]),
+    );
+    let jsonl = embeddings
+        .to_jsonl()
+        .expect("error writing embeddings batch");
+    let line: serde_json::Value = serde_json::from_str(jsonl.trim()).expect("invalid line");
+    assert_eq!(line["url"], "/v1/embeddings");
+    assert_eq!(line["body"]["input"], json!(["toad"]));
+}
+
+fn model_ids(models: &[&OpenAIModel]) -> Vec<String> {
+    models.iter().map(|model| model.id.clone()).collect()
+}
+
+#[tokio::test]
+pub async fn test_model_filters_from_outside_crate() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/models"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "object": "list",
+            "data": [
+                { "id": "gpt-4o", "object": "model", "created": 1715367049, "owned_by": "system" },
+                {
+                    "id": "ft:gpt-4o-mini-2024-07-18:toads::abc123",
+                    "object": "model",
+                    "created": 1725000000,
+                    "owned_by": "user-toads"
+                }
+            ]
+        })))
+        .mount(&server)
+        .await;
+
+    let client = OpenAIClient::new("test-key", &server.uri());
+    let models: OpenAIGetModelsResponse = client.get_models().await.expect("error listing models");
+    assert_eq!(model_ids(&models.base_models()), ["gpt-4o"]);
+    assert_eq!(
+        model_ids(&models.fine_tuned()),
+        ["ft:gpt-4o-mini-2024-07-18:toads::abc123"]
+    );
+    assert_eq!(model_ids(&models.owned_by("system")), ["gpt-4o"]);
+    assert_eq!(model_ids(&models.created_after(1720000000)).len(), 1);
+    assert_eq!(model_ids(&models.created_before(1720000000)), ["gpt-4o"]);
+}
+
+#[tokio::test]
+pub async fn test_moderation_gate_from_outside_crate() {
+    let categories = |value: serde_json::Value| {
+        json!({
+            "harassment": value, "harassment/threatening": value, "hate": value,
+            "hate/threatening": value, "self-harm": value, "self-harm/intent": value,
+            "self-harm/instructions": value, "sexual": value, "sexual/minors": value,
+            "violence": value, "violence/graphic": value
+        })
+    };
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/moderations"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "modr-1",
+            "model": "omni-moderation-latest",
+            "results": [{
+                "flagged": true,
+                "categories": categories(json!(true)),
+                "category_scores": categories(json!(0.9))
+            }]
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let gate = ModerationGate {
+        model: Some("omni-moderation-latest".to_owned()),
+        moderate_output: true,
+    };
+    let client = OpenAIClient::new("test-key", &server.uri()).with_moderation(gate);
+    let opts = ChatOptions::default(
+        "gpt-4o-mini",
+        vec![ChatMessage::new(ChatRole::User, "something awful")],
+        20,
+    );
+    let err = client
+        .get_chat_completion(&opts)
+        .await
+        .expect_err("flagged input was sent");
+    let flagged = err
+        .downcast_ref::<InputFlagged>()
+        .expect("expected InputFlagged");
+    assert_eq!(flagged.input, "something awful");
+}